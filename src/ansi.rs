@@ -0,0 +1,227 @@
+//! Derives a 16-color ANSI terminal palette from the extracted primary, text, and accent colors.
+//!
+//! Each ANSI hue slot (red, green, yellow, blue, magenta, cyan) is filled from the extracted color closest in OKLCH hue. When the image has no color near the canonical hue, the nearest one is shifted toward it, so every slot stays recognizable while keeping the wallpaper's character.
+use crate::color;
+use crate::config::{AnsiPalette, Palette};
+use crate::error::WallbashError;
+
+/// Canonical OKLCH hues, in degrees, for red, green, yellow, blue, magenta, and cyan.
+const ANSI_HUES: [f64; 6] = [29.0, 142.0, 110.0, 264.0, 328.0, 195.0];
+/// Maximum distance, in degrees, a slot's hue may stray from its canonical hue.
+const HUE_TOLERANCE: f64 = 20.0;
+/// Degrees kept back from `HUE_TOLERANCE` so rounding to 8-bit hex can't push a slot past it.
+const HUE_ROUNDING_MARGIN: f64 = 2.0;
+/// Extracted colors with less chroma than this are treated as gray and never fill a hue slot.
+const MIN_SOURCE_CHROMA: f64 = 0.03;
+const MIN_CHROMA: f64 = 0.08;
+const MAX_CHROMA: f64 = 0.18;
+const DARK_MODE_LIGHTNESS: f64 = 0.72;
+const LIGHT_MODE_LIGHTNESS: f64 = 0.5;
+/// Lightness added to a normal color to produce its bright variant (color8 to color15).
+const BRIGHT_STEP: f64 = 0.08;
+/// Lightness added to the background to produce bright black (color8).
+const BRIGHT_BLACK_STEP: f64 = 0.15;
+/// Lightness the selection background moves from the background toward the foreground.
+const SELECTION_STEP: f64 = 0.15;
+
+/// Signed shortest distance from hue `from` to hue `to`, in degrees.
+fn hue_delta(from: f64, to: f64) -> f64 {
+    (to - from + 540.0).rem_euclid(360.0) - 180.0
+}
+
+fn shift_lightness(hex: &str, delta: f64) -> Result<String, WallbashError> {
+    let (l, c, h) = color::rgb_to_oklch(color::parse_hex(hex)?);
+    Ok(color::to_hex(color::oklch_to_rgb(l + delta, c, h)))
+}
+
+/// Picks the (hue, chroma) for an ANSI slot from the candidate colors' OKLCH values.
+fn slot_hue_chroma(canonical: f64, candidates: &[(f64, f64, f64)]) -> (f64, f64) {
+    let nearest = candidates
+        .iter()
+        .filter(|(_, c, _)| *c >= MIN_SOURCE_CHROMA)
+        .min_by(|a, b| {
+            hue_delta(canonical, a.2)
+                .abs()
+                .total_cmp(&hue_delta(canonical, b.2).abs())
+        });
+
+    match nearest {
+        Some(&(_, chroma, hue)) => {
+            let limit = HUE_TOLERANCE - HUE_ROUNDING_MARGIN;
+            let delta = hue_delta(canonical, hue).clamp(-limit, limit);
+            (
+                (canonical + delta).rem_euclid(360.0),
+                chroma.clamp(MIN_CHROMA, MAX_CHROMA),
+            )
+        }
+        None => (canonical, MIN_CHROMA),
+    }
+}
+
+/// Generates the ANSI palette for a palette whose primary, text, and accent colors are already filled in.
+pub fn generate_ansi(palette: &Palette) -> Result<AnsiPalette, WallbashError> {
+    let (background, foreground) = match (palette.primary.first(), palette.text.first()) {
+        (Some(pry), Some(txt)) => (pry.clone(), txt.clone()),
+        _ => {
+            return Err(WallbashError::InvalidInput(
+                "Cannot build ANSI palette without primary and text colors".to_string(),
+            ));
+        }
+    };
+
+    let mut candidates = Vec::new();
+    for hex in palette
+        .primary
+        .iter()
+        .chain(palette.accents.iter().flatten())
+    {
+        candidates.push(color::rgb_to_oklch(color::parse_hex(hex)?));
+    }
+
+    let lightness = if palette.is_dark {
+        DARK_MODE_LIGHTNESS
+    } else {
+        LIGHT_MODE_LIGHTNESS
+    };
+    let mut normal = Vec::with_capacity(ANSI_HUES.len());
+    let mut bright = Vec::with_capacity(ANSI_HUES.len());
+    for canonical in ANSI_HUES {
+        let (hue, chroma) = slot_hue_chroma(canonical, &candidates);
+        normal.push(color::to_hex(color::oklch_to_rgb(lightness, chroma, hue)));
+        bright.push(color::to_hex(color::oklch_to_rgb(
+            lightness + BRIGHT_STEP,
+            chroma,
+            hue,
+        )));
+    }
+
+    let (black, white) = if palette.is_dark {
+        (background.clone(), foreground.clone())
+    } else {
+        (foreground.clone(), background.clone())
+    };
+    let bright_black = shift_lightness(&black, BRIGHT_BLACK_STEP)?;
+    let bright_white = shift_lightness(&white, BRIGHT_STEP)?;
+    let (bg_lightness, _, _) = color::rgb_to_oklch(color::parse_hex(&background)?);
+    let (fg_lightness, _, _) = color::rgb_to_oklch(color::parse_hex(&foreground)?);
+    let selection = shift_lightness(
+        &background,
+        SELECTION_STEP.copysign(fg_lightness - bg_lightness),
+    )?;
+
+    Ok(AnsiPalette {
+        color0: black,
+        color1: normal[0].clone(),
+        color2: normal[1].clone(),
+        color3: normal[2].clone(),
+        color4: normal[3].clone(),
+        color5: normal[4].clone(),
+        color6: normal[5].clone(),
+        color7: white,
+        color8: bright_black,
+        color9: bright[0].clone(),
+        color10: bright[1].clone(),
+        color11: bright[2].clone(),
+        color12: bright[3].clone(),
+        color13: bright[4].clone(),
+        color14: bright[5].clone(),
+        color15: bright_white,
+        cursor: foreground.clone(),
+        foreground,
+        background,
+        selection,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette(is_dark: bool, primary: &str, text: &str) -> Palette {
+        Palette {
+            is_dark,
+            primary: vec![primary.to_string()],
+            text: vec![text.to_string()],
+            accents: vec![vec!["C5320E".to_string(), "3ACDF1".to_string()]],
+            ..Default::default()
+        }
+    }
+
+    fn assert_readable_selection(palette: &Palette) {
+        let ansi = generate_ansi(palette).unwrap();
+        let text = color::contrast_ratio(&ansi.foreground, &ansi.selection).unwrap();
+        let background = color::contrast_ratio(&ansi.background, &ansi.selection).unwrap();
+        assert!(
+            text >= 4.5,
+            "foreground on #{} has contrast {:.2}",
+            ansi.selection,
+            text
+        );
+        assert!(
+            background > 1.2,
+            "#{} blends into #{}",
+            ansi.selection,
+            ansi.background
+        );
+    }
+
+    #[test]
+    fn selection_is_readable_in_dark_mode() {
+        assert_readable_selection(&palette(true, "050910", "FFFFFF"));
+    }
+
+    #[test]
+    fn selection_is_readable_in_light_mode() {
+        assert_readable_selection(&palette(false, "F2EEE8", "12100F"));
+    }
+
+    fn hue_of(hex: &str) -> f64 {
+        color::rgb_to_oklch(color::parse_hex(hex).unwrap()).2
+    }
+
+    #[test]
+    fn green_slot_stays_green_without_green_in_the_palette() {
+        // The fixture accents are an orange-red and a cyan; nothing is near green.
+        for palette in [
+            palette(true, "050910", "FFFFFF"),
+            palette(false, "F2EEE8", "12100F"),
+        ] {
+            let ansi = generate_ansi(&palette).unwrap();
+            for hex in [&ansi.color2, &ansi.color10] {
+                let hue = hue_of(hex);
+                assert!(
+                    hue_delta(142.0, hue).abs() <= HUE_TOLERANCE,
+                    "#{} has hue {:.1}",
+                    hex,
+                    hue
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn slot_takes_the_nearest_hue_within_tolerance() {
+        let candidates = [(0.6, 0.12, 35.0), (0.6, 0.12, 200.0)];
+        let (hue, chroma) = slot_hue_chroma(29.0, &candidates);
+        assert!((hue - 35.0).abs() < 1e-9);
+        assert!((chroma - 0.12).abs() < 1e-9);
+    }
+
+    #[test]
+    fn slot_hue_is_clamped_to_the_tolerance() {
+        let candidates = [(0.6, 0.12, 200.0)];
+        let (hue, _) = slot_hue_chroma(142.0, &candidates);
+        let limit = HUE_TOLERANCE - HUE_ROUNDING_MARGIN;
+        assert!((hue - (142.0 + limit)).abs() < 1e-9);
+        // Clamping also works across 0 degrees.
+        let (hue, _) = slot_hue_chroma(29.0, &[(0.6, 0.12, 300.0)]);
+        assert!((hue - (29.0 - limit)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn gray_candidates_fall_back_to_the_canonical_hue() {
+        let candidates = [(0.5, MIN_SOURCE_CHROMA / 2.0, 140.0)];
+        assert_eq!(slot_hue_chroma(264.0, &candidates), (264.0, MIN_CHROMA));
+        assert_eq!(slot_hue_chroma(264.0, &[]), (264.0, MIN_CHROMA));
+    }
+}
@@ -0,0 +1,242 @@
+//! Atomic file writes with rollback for a whole output run.
+//!
+//! Files are written to a temporary sibling, fsynced and renamed over the destination, so readers such as kitty or Hyprland never see a half-written config. A `Transaction` also snapshots every file before replacing it, letting a failed run put back what it already changed. In dry-run mode it writes nothing and instead reports each target with a unified diff on stderr, leaving stdout to `--print`.
+use crate::error::WallbashError;
+use log::{info, warn};
+use similar::TextDiff;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Atomically replaces `path` with `contents`, creating parent directories as needed.
+///
+/// Symlinks are followed so the link itself is kept, and an existing file's permissions carry over to the new one.
+pub fn write_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let target = if fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink()) {
+        fs::canonicalize(path)?
+    } else {
+        path.to_path_buf()
+    };
+    let dir = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    fs::create_dir_all(&dir)?;
+
+    let file_name = target.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "output path has no file name")
+    })?;
+    let temp_path = dir.join(format!(
+        ".{}.{}.wr-tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = (|| {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents)?;
+        if let Ok(metadata) = fs::metadata(&target) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp_path, &target)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+        return result;
+    }
+
+    // Persist the rename itself; not every platform can open a directory for this.
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(&dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// Reports on stderr whether `path` would be created, modified, or left unchanged, followed by a unified diff.
+fn report_change(path: &Path, contents: &[u8]) -> Result<(), WallbashError> {
+    let current = match fs::read(path) {
+        Ok(bytes) => Some(bytes),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let status = match &current {
+        None => "create",
+        Some(bytes) if bytes == contents => {
+            eprintln!("Unchanged: {}", path.display());
+            return Ok(());
+        }
+        Some(_) => "modify",
+    };
+    eprintln!("Would {}: {}", status, path.display());
+
+    let (Ok(old), Ok(new)) = (
+        std::str::from_utf8(current.as_deref().unwrap_or_default()),
+        std::str::from_utf8(contents),
+    ) else {
+        eprintln!("Binary file, {} bytes", contents.len());
+        return Ok(());
+    };
+    let old_header = if current.is_some() {
+        path.display().to_string()
+    } else {
+        "/dev/null".to_string()
+    };
+    let diff = TextDiff::from_lines(old, new);
+    eprint!(
+        "{}",
+        diff.unified_diff()
+            .header(&old_header, &path.display().to_string())
+    );
+    Ok(())
+}
+
+/// Tracks the files replaced during one output run so they can be restored if the run fails.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    /// Report what would change instead of writing.
+    dry_run: bool,
+    /// Original contents of each written path, or `None` if it did not exist, in write order.
+    snapshots: Vec<(PathBuf, Option<Vec<u8>>)>,
+}
+
+impl Transaction {
+    pub fn new(dry_run: bool) -> Self {
+        Self {
+            dry_run,
+            snapshots: Vec::new(),
+        }
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Snapshots `path` on its first write in this run, then atomically replaces it.
+    ///
+    /// A failed write leaves the destination untouched, so only successful writes are recorded.
+    pub fn write(&mut self, path: &Path, contents: &[u8]) -> Result<(), WallbashError> {
+        if self.dry_run {
+            return report_change(path, contents);
+        }
+        if self.snapshots.iter().any(|(p, _)| p == path) {
+            write_file(path, contents)?;
+            return Ok(());
+        }
+        let original = match fs::read(path) {
+            Ok(bytes) => Some(bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        write_file(path, contents)?;
+        self.snapshots.push((path.to_path_buf(), original));
+        Ok(())
+    }
+
+    /// Restores every file written in this run to its pre-run state, newest first.
+    pub fn rollback(self) {
+        for (path, original) in self.snapshots.into_iter().rev() {
+            let result = match &original {
+                Some(bytes) => write_file(&path, bytes),
+                None => match fs::remove_file(&path) {
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                    other => other,
+                },
+            };
+            match result {
+                Ok(()) if original.is_some() => info!("Restored {}", path.display()),
+                Ok(()) => info!("Removed {}", path.display()),
+                Err(e) => warn!("Failed to restore '{}': {}", path.display(), e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("wallrust-atomic-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn rollback_restores_earlier_contents() {
+        let dir = temp_dir("restore");
+        let path = dir.join("kitty.conf");
+        fs::write(&path, "before").unwrap();
+
+        let mut transaction = Transaction::new(false);
+        transaction.write(&path, b"first").unwrap();
+        transaction.write(&path, b"second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        transaction.rollback();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "before");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rollback_deletes_created_files() {
+        let dir = temp_dir("created");
+        let mut transaction = Transaction::new(false);
+        transaction.write(&dir.join("new.css"), b"created").unwrap();
+        transaction
+            .write(&dir.join("nested/new.conf"), b"created")
+            .unwrap();
+        transaction.rollback();
+
+        assert_eq!(file_names(&dir), vec!["nested"]);
+        assert!(file_names(&dir.join("nested")).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn dry_run_touches_nothing() {
+        let dir = temp_dir("dry-run");
+        let existing = dir.join("existing.conf");
+        fs::write(&existing, "before").unwrap();
+
+        let mut transaction = Transaction::new(true);
+        transaction.write(&existing, b"after").unwrap();
+        transaction
+            .write(&dir.join("new.conf"), b"created")
+            .unwrap();
+        transaction
+            .write(&dir.join("nested/new.conf"), b"created")
+            .unwrap();
+        transaction.rollback();
+
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "before");
+        assert_eq!(file_names(&dir), vec!["existing.conf"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_write_leaves_no_temp_file() {
+        let dir = temp_dir("failed");
+        // Renaming a file over a non-empty directory fails after the temporary file was written.
+        let target = dir.join("waybar.css");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("keep"), "").unwrap();
+
+        assert!(write_file(&target, b"contents").is_err());
+        assert_eq!(file_names(&dir), vec!["waybar.css"]);
+        assert!(target.is_dir());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
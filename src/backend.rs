@@ -0,0 +1,76 @@
+//! Defines the color extraction backend abstraction used by palette generation.
+//!
+//! This module provides the `ColorBackend` trait, which both the ImageMagick (`magick` CLI) and the native Rust implementations satisfy, and the `BackendKind` selector exposed via `--backend`.
+use crate::config::AppPaths;
+use crate::error::WallbashError;
+use crate::imagemagick::MagickBackend;
+use crate::native::NativeBackend;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Selects which implementation performs image analysis and color operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// Shell out to the ImageMagick `magick` command.
+    #[default]
+    Magick,
+    /// Decode the image and run k-means in-process.
+    Native,
+}
+
+impl std::fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendKind::Magick => write!(f, "magick"),
+            BackendKind::Native => write!(f, "native"),
+        }
+    }
+}
+
+/// Whole-image analysis operations required to build a palette.
+///
+/// Queries operate on the image passed to `load_image`. Per-color math lives in the `color` module and needs no backend.
+pub trait ColorBackend {
+    /// Returns which backend this is, used for logging and cache validation.
+    fn kind(&self) -> BackendKind;
+
+    /// Returns the version of the extraction implementation, used to key the cache.
+    fn version(&self) -> String;
+
+    /// Validates and loads the image that subsequent whole-image queries operate on.
+    fn load_image(&mut self, image_path: &Path) -> Result<(), WallbashError>;
+
+    /// Extracts k-means colors from the loaded image, returning (pixel count, hex) tuples sorted by count descending.
+    fn extract_kmeans_colors(
+        &self,
+        colors: usize,
+        fuzz: u8,
+    ) -> Result<Vec<(u64, String)>, WallbashError>;
+
+    /// Checks if the loaded image is dark by analyzing its mean brightness.
+    fn is_image_dark(&self) -> Result<bool, WallbashError>;
+
+    /// Returns the average HSL saturation of the loaded image, used to detect grayscale images.
+    fn average_saturation(&self) -> Result<f64, WallbashError>;
+
+    /// Generates a thumbnail image for color extraction, resizing and centering the input image.
+    fn generate_thumbnail(
+        &self,
+        input_path: &Path,
+        thumbnail_path: &Path,
+    ) -> Result<(), WallbashError>;
+}
+
+/// Constructs the backend selected on the command line.
+///
+/// With `dry_run`, the ImageMagick backend keeps its MPC cache in the temp directory instead of the cache directory.
+pub fn create_backend(kind: BackendKind, paths: &AppPaths, dry_run: bool) -> Box<dyn ColorBackend> {
+    match kind {
+        BackendKind::Magick if dry_run => Box::new(MagickBackend::new(
+            std::env::temp_dir().join(format!("wallrust-{}.mpc", std::process::id())),
+        )),
+        BackendKind::Magick => Box::new(MagickBackend::new(paths.mpc_cache_file.clone())),
+        BackendKind::Native => Box::new(NativeBackend::new()),
+    }
+}
@@ -0,0 +1,338 @@
+//! Maps palettes to and from Base16/Base24 color schemes.
+//!
+//! Exported schemes take their colors from the ANSI palette: `base00`-`base07` form a ramp from the background to the foreground and beyond, `base08`-`base0F` are the hues, and Base24's `base10`-`base17` add darker backgrounds and the bright hues. Imported schemes, in the current tinted-theming format or the legacy flat one, become a palette whose primaries are the background and the blue, magenta, and cyan hues.
+use crate::color;
+use crate::config::{ACCENT_COUNT, AnsiPalette, Palette};
+use crate::error::WallbashError;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Number of keys in a Base16 scheme; Base24 adds eight more.
+pub const BASE16_LEN: usize = 16;
+/// Number of keys in a Base24 scheme.
+pub const BASE24_LEN: usize = 24;
+
+/// Returns the scheme key for an index, e.g. `base0A` for 10 and `base17` for 23.
+pub fn key(index: usize) -> String {
+    format!("base{:02X}", index)
+}
+
+/// Linearly mixes two hex colors, `weight` 0 giving `from` and 1 giving `to`.
+fn mix(from: &str, to: &str, weight: f64) -> Result<String, WallbashError> {
+    let from = color::parse_hex(from)?;
+    let to = color::parse_hex(to)?;
+    Ok(color::to_hex(
+        [0, 1, 2].map(|c| from[c] + (to[c] - from[c]) * weight),
+    ))
+}
+
+/// Returns the Base24 colors for a palette, `base00` through `base17`, as hex without `#`.
+///
+/// The first sixteen are the palette's Base16 scheme.
+pub fn scheme(palette: &Palette) -> Result<Vec<String>, WallbashError> {
+    let ansi = &palette.ansi;
+    if ansi.is_empty() {
+        return Err(WallbashError::InvalidInput(
+            "Palette has no terminal colors to build a base16 scheme from".to_string(),
+        ));
+    }
+    let (bg, fg) = (ansi.background.as_str(), ansi.foreground.as_str());
+    // Past the foreground lies white on dark schemes and black on light ones; past the background, the opposite.
+    let (beyond_fg, beyond_bg) = if palette.is_dark {
+        ("FFFFFF", "000000")
+    } else {
+        ("000000", "FFFFFF")
+    };
+
+    Ok(vec![
+        bg.to_string(),
+        mix(bg, fg, 0.08)?,
+        mix(bg, fg, 0.18)?,
+        mix(bg, fg, 0.42)?,
+        mix(bg, fg, 0.7)?,
+        fg.to_string(),
+        mix(fg, beyond_fg, 0.4)?,
+        mix(fg, beyond_fg, 0.75)?,
+        ansi.color1.clone(),
+        mix(&ansi.color1, &ansi.color3, 0.5)?,
+        ansi.color3.clone(),
+        ansi.color2.clone(),
+        ansi.color6.clone(),
+        ansi.color4.clone(),
+        ansi.color5.clone(),
+        mix(&ansi.color1, bg, 0.4)?,
+        mix(bg, beyond_bg, 0.3)?,
+        mix(bg, beyond_bg, 0.6)?,
+        ansi.color9.clone(),
+        ansi.color11.clone(),
+        ansi.color10.clone(),
+        ansi.color14.clone(),
+        ansi.color12.clone(),
+        ansi.color13.clone(),
+    ])
+}
+
+/// A Base16 or Base24 scheme file, with the colors either under `palette` or at the top level.
+#[derive(Deserialize)]
+struct SchemeFile {
+    #[serde(default)]
+    variant: Option<String>,
+    #[serde(default)]
+    palette: HashMap<String, String>,
+    #[serde(flatten)]
+    top_level: HashMap<String, serde_yaml::Value>,
+}
+
+/// Parses a Base16 or Base24 YAML scheme into a palette with primaries, text, accents, and ANSI colors.
+///
+/// RGBA strings and text contrast are left for the caller to compute.
+pub fn parse_scheme(content: &str) -> Result<Palette, WallbashError> {
+    let mut file: SchemeFile = serde_yaml::from_str(content)?;
+    if file.palette.is_empty() {
+        file.palette = file
+            .top_level
+            .into_iter()
+            .filter_map(|(k, v)| Some((k, v.as_str()?.to_string())))
+            .collect();
+    }
+
+    let mut colors = Vec::with_capacity(BASE24_LEN);
+    for i in 0..BASE24_LEN {
+        let name = key(i);
+        let value = file
+            .palette
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(&name))
+            .map(|(_, v)| v.trim().trim_start_matches('#').to_uppercase());
+        match value {
+            Some(hex) => {
+                color::parse_hex(&hex)?;
+                colors.push(hex);
+            }
+            None if i < BASE16_LEN => {
+                return Err(WallbashError::InvalidInput(format!(
+                    "base16 scheme is missing {}",
+                    name
+                )));
+            }
+            None => break,
+        }
+    }
+    let base = |i: usize| colors[i].clone();
+    // Base24 bright hues, falling back to the normal ones for Base16 schemes.
+    let bright =
+        |i: usize, fallback: usize| colors.get(i).cloned().unwrap_or_else(|| base(fallback));
+
+    let is_dark = match file.variant.as_deref() {
+        Some("light") => false,
+        Some("dark") => true,
+        _ => color::is_dark(&colors[0])?,
+    };
+
+    let mut palette = Palette {
+        mode: if is_dark { "dark" } else { "light" }.to_string(),
+        is_dark,
+        ..Default::default()
+    };
+    // Each primary with the hue its accent ramp runs through; the background's ramp uses blue.
+    for (pry, hue) in [(0x00, 0x0D), (0x0D, 0x0D), (0x0E, 0x0E), (0x0C, 0x0C)] {
+        let primary = base(pry);
+        let text = [base(0x05), base(0x00)]
+            .into_iter()
+            .map(|txt| Ok((color::contrast_ratio(&txt, &primary)?, txt)))
+            .collect::<Result<Vec<_>, WallbashError>>()?
+            .into_iter()
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, txt)| txt)
+            .unwrap_or_default();
+        palette.primary.push(primary);
+        palette.text.push(text);
+        palette
+            .accents
+            .push(accent_ramp(&base(0x00), &base(hue), &base(0x07))?);
+    }
+
+    palette.ansi = AnsiPalette {
+        color0: base(0x00),
+        color1: base(0x08),
+        color2: base(0x0B),
+        color3: base(0x0A),
+        color4: base(0x0D),
+        color5: base(0x0E),
+        color6: base(0x0C),
+        color7: base(0x05),
+        color8: base(0x03),
+        color9: bright(0x12, 0x08),
+        color10: bright(0x14, 0x0B),
+        color11: bright(0x13, 0x0A),
+        color12: bright(0x16, 0x0D),
+        color13: bright(0x17, 0x0E),
+        color14: bright(0x15, 0x0C),
+        color15: base(0x07),
+        foreground: base(0x05),
+        background: base(0x00),
+        cursor: base(0x05),
+        selection: base(0x02),
+    };
+    Ok(palette)
+}
+
+/// Builds `ACCENT_COUNT` accents from the background through `hue` (in the middle) towards `base07`.
+fn accent_ramp(background: &str, hue: &str, far_end: &str) -> Result<Vec<String>, WallbashError> {
+    let half = ACCENT_COUNT / 2;
+    let step = 1.0 / (half + 1) as f64;
+    (0..ACCENT_COUNT)
+        .map(|j| match j.cmp(&half) {
+            std::cmp::Ordering::Less => mix(background, hue, (j + 1) as f64 * step),
+            std::cmp::Ordering::Equal => Ok(hue.to_string()),
+            std::cmp::Ordering::Greater => mix(hue, far_end, (j - half) as f64 * step),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// base16-ocean in the legacy flat format, with colors at the top level and without `#`.
+    const LEGACY_OCEAN: &str = r#"
+scheme: "Ocean"
+author: "Chris Kempson (http://chriskempson.com)"
+base00: "2b303b"
+base01: "343d46"
+base02: "4f5b66"
+base03: "65737e"
+base04: "a7adba"
+base05: "c0c5ce"
+base06: "dfe1e8"
+base07: "eff1f5"
+base08: "bf616a"
+base09: "d08770"
+base0A: "ebcb8b"
+base0B: "a3be8c"
+base0C: "96b5b4"
+base0D: "8fa1b3"
+base0E: "b48ead"
+base0F: "ab7967"
+"#;
+
+    #[test]
+    fn scheme_maps_hues_onto_ansi_colors() {
+        let mut palette = Palette::default();
+        for (i, value) in palette.ansi.values_mut().into_iter().enumerate() {
+            *value = format!("{:06X}", i);
+        }
+        let scheme = scheme(&palette).unwrap();
+        assert_eq!(scheme.len(), BASE24_LEN);
+        // base08 red, base0A yellow, base0B green, base0C cyan, base0D blue, base0E magenta.
+        for (base, ansi) in [
+            (0x08, 1),
+            (0x0A, 3),
+            (0x0B, 2),
+            (0x0C, 6),
+            (0x0D, 4),
+            (0x0E, 5),
+        ] {
+            assert_eq!(scheme[base], format!("{:06X}", ansi), "{}", key(base));
+        }
+        // Base24's bright hues use the bright ANSI colors in the same order.
+        for (base, ansi) in [
+            (0x12, 9),
+            (0x13, 11),
+            (0x14, 10),
+            (0x15, 14),
+            (0x16, 12),
+            (0x17, 13),
+        ] {
+            assert_eq!(scheme[base], format!("{:06X}", ansi), "{}", key(base));
+        }
+        assert_eq!(scheme[0x00], palette.ansi.background);
+        assert_eq!(scheme[0x05], palette.ansi.foreground);
+    }
+
+    #[test]
+    fn parses_legacy_flat_schemes() {
+        let palette = parse_scheme(LEGACY_OCEAN).unwrap();
+        assert_eq!(palette.mode, "dark");
+        assert_eq!(palette.primary, ["2B303B", "8FA1B3", "B48EAD", "96B5B4"]);
+        assert_eq!(palette.accents.len(), palette.primary.len());
+        assert!(
+            palette
+                .accents
+                .iter()
+                .all(|ramp| ramp.len() == ACCENT_COUNT)
+        );
+        assert_eq!(palette.accents[0][ACCENT_COUNT / 2], "8FA1B3");
+
+        let ansi = &palette.ansi;
+        assert_eq!(
+            [
+                &ansi.color1,
+                &ansi.color2,
+                &ansi.color3,
+                &ansi.color4,
+                &ansi.color5,
+                &ansi.color6
+            ],
+            ["BF616A", "A3BE8C", "EBCB8B", "8FA1B3", "B48EAD", "96B5B4"]
+        );
+        // Without Base24 colors, the bright hues repeat the normal ones.
+        assert_eq!(ansi.color9, "BF616A");
+        assert_eq!(
+            (ansi.background.as_str(), ansi.foreground.as_str()),
+            ("2B303B", "C0C5CE")
+        );
+    }
+
+    #[test]
+    fn parses_tinted_schemes_with_variant() {
+        let content = r##"
+system: "base24"
+name: "Ocean Light"
+variant: "light"
+palette:
+  base00: "#eff1f5"
+  base01: "#dfe1e8"
+  base02: "#c0c5ce"
+  base03: "#a7adba"
+  base04: "#65737e"
+  base05: "#2b303b"
+  base06: "#343d46"
+  base07: "#4f5b66"
+  base08: "#bf616a"
+  base09: "#d08770"
+  base0A: "#ebcb8b"
+  base0B: "#a3be8c"
+  base0C: "#96b5b4"
+  base0D: "#8fa1b3"
+  base0E: "#b48ead"
+  base0F: "#ab7967"
+  base10: "#f5f7fa"
+  base11: "#ffffff"
+  base12: "#d0727b"
+  base13: "#f2d69c"
+  base14: "#b4cf9d"
+  base15: "#a7c6c5"
+  base16: "#a0b2c4"
+  base17: "#c59fbe"
+"##;
+        let palette = parse_scheme(content).unwrap();
+        assert_eq!(palette.mode, "light");
+        assert_eq!(palette.primary[0], "EFF1F5");
+        assert_eq!(palette.ansi.color4, "8FA1B3");
+        assert_eq!(
+            [
+                &palette.ansi.color9,
+                &palette.ansi.color10,
+                &palette.ansi.color12
+            ],
+            ["D0727B", "B4CF9D", "A0B2C4"]
+        );
+    }
+
+    #[test]
+    fn rejects_schemes_missing_a_base16_color() {
+        let content = LEGACY_OCEAN.replace("base0F: \"ab7967\"\n", "");
+        assert!(parse_scheme(&content).is_err());
+    }
+}
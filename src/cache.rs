@@ -1,12 +1,22 @@
 //! Handles palette caching, cache validation, and cache file I/O for Wallrust.
 //!
-//! This module provides functions to read, write, and validate palette caches, ensuring that color extraction is only performed when necessary. Caching is based on image checksum, color profile, extraction mode, and other relevant parameters.
-use crate::config::{CacheData, ColorProfile, Palette, SortMode};
+//! Palettes are cached in a content-addressed directory with one entry per image checksum and combination of extraction settings (profile, sort mode, colors, fuzz, wallset, backend and its version, ...), so switching between wallpapers or settings reuses earlier results. Entries are evicted least recently used first once the configured entry count or size limit is exceeded.
+//!
+//! Image checksums are remembered per path together with the file's size, mtime, and inode, so unchanged wallpapers are not re-hashed on every run.
+use crate::atomic;
+use crate::config::{AppPaths, CacheConfig, CacheData, ExtractionSettings, FileStamp, Palette};
 use crate::error::WallbashError;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::{Read, Write};
-use std::path::Path;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Name of the single-entry cache file used by earlier versions, removed by `cache clear`.
+const LEGACY_CACHE_FILE: &str = "wallbash_cache.json";
 
 fn calculate_checksum(file_path: &Path) -> Result<String, WallbashError> {
     let mut file = File::open(file_path).map_err(|e| {
@@ -30,26 +40,179 @@ fn calculate_checksum(file_path: &Path) -> Result<String, WallbashError> {
     Ok(hex::encode(hash))
 }
 
-/// Reads the palette cache from a file, returning None if the cache does not exist or is invalid.
+/// The content checksum of an image together with the file stamp it was computed for.
+pub struct ImageChecksum {
+    pub checksum: String,
+    pub stamp: FileStamp,
+}
+
+/// A checksum remembered for an image path in the checksum index.
+#[derive(Serialize, Deserialize)]
+struct IndexedChecksum {
+    stamp: FileStamp,
+    checksum: String,
+}
+
+/// Returns the canonical form of an image path, which identifies the image in the index and in cache entries
+/// regardless of the working directory it was given relative to.
+fn canonical_path(image_path: &Path) -> String {
+    fs::canonicalize(image_path)
+        .unwrap_or_else(|_| image_path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// Reads the checksum index, starting over with an empty one if it is missing or unreadable.
+fn read_index(index_file: &Path) -> HashMap<String, IndexedChecksum> {
+    let content = match fs::read_to_string(index_file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return HashMap::new(),
+        Err(e) => {
+            warn!(
+                "Failed to read checksum index '{}': {}",
+                index_file.display(),
+                e
+            );
+            return HashMap::new();
+        }
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        warn!(
+            "Failed to parse checksum index '{}', rebuilding it: {}",
+            index_file.display(),
+            e
+        );
+        HashMap::new()
+    })
+}
+
+fn write_index(
+    index_file: &Path,
+    index: &HashMap<String, IndexedChecksum>,
+) -> Result<(), WallbashError> {
+    atomic::write_file(index_file, serde_json::to_string(index)?.as_bytes())?;
+    Ok(())
+}
+
+/// Returns the checksum of an image, reusing the remembered one while the file's size, mtime, and inode are unchanged.
+///
+/// With `strict`, the image is always hashed. With `dry_run`, a new checksum is not remembered.
+pub fn image_checksum(
+    index_file: &Path,
+    image_path: &Path,
+    strict: bool,
+    dry_run: bool,
+) -> Result<ImageChecksum, WallbashError> {
+    let stamp = FileStamp::of(image_path)?;
+    let key = canonical_path(image_path);
+    let mut index = read_index(index_file);
+
+    if !strict
+        && let Some(known) = index.get(&key)
+        && known.stamp == stamp
+    {
+        debug!("Image unchanged (size, mtime, inode), reusing its checksum.");
+        return Ok(ImageChecksum {
+            checksum: known.checksum.clone(),
+            stamp,
+        });
+    }
+
+    let checksum = calculate_checksum(image_path)?;
+    if dry_run {
+        return Ok(ImageChecksum { checksum, stamp });
+    }
+    index.insert(
+        key,
+        IndexedChecksum {
+            stamp: stamp.clone(),
+            checksum: checksum.clone(),
+        },
+    );
+    write_index(index_file, &index)?;
+    Ok(ImageChecksum { checksum, stamp })
+}
+
+/// Returns the path of the cache entry for an image checksum and extraction settings.
+fn entry_path(
+    cache_dir: &Path,
+    checksum: &str,
+    settings: &ExtractionSettings,
+) -> Result<PathBuf, WallbashError> {
+    let mut hasher = Sha256::new();
+    hasher.update(checksum.as_bytes());
+    hasher.update(serde_json::to_vec(settings)?);
+    Ok(cache_dir.join(format!("{}.json", hex::encode(hasher.finalize()))))
+}
+
+/// Marks a cache entry as just used by bumping its modification time, which drives LRU eviction.
+fn touch(entry: &Path) {
+    let result = File::options()
+        .append(true)
+        .open(entry)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+    if let Err(e) = result {
+        warn!(
+            "Failed to update cache entry time '{}': {}",
+            entry.display(),
+            e
+        );
+    }
+}
+
+/// A cache entry file with the metadata needed for listing and eviction.
+struct EntryFile {
+    path: PathBuf,
+    last_used: SystemTime,
+    size: u64,
+}
+
+/// Returns all cache entries, most recently used first.
+fn entry_files(cache_dir: &Path) -> Result<Vec<EntryFile>, WallbashError> {
+    if !cache_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(cache_dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let metadata = fs::metadata(&path)?;
+        entries.push(EntryFile {
+            path,
+            last_used: metadata.modified()?,
+            size: metadata.len(),
+        });
+    }
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_used));
+    Ok(entries)
+}
+
+/// Reads a palette cache entry, returning None if it does not exist or is invalid.
 pub fn read_cache(cache_file: &Path) -> Result<Option<CacheData>, WallbashError> {
     if !cache_file.exists() {
-        println!("Cache file does not exist: {}", cache_file.display());
+        debug!("Cache file does not exist: {}", cache_file.display());
         return Ok(None);
     }
     
     let content = fs::read_to_string(cache_file)?;
-    println!("Read cache file: {} ({} bytes)", cache_file.display(), content.len());
-    
+    debug!(
+        "Read cache file: {} ({} bytes)",
+        cache_file.display(),
+        content.len()
+    );
+
     match serde_json::from_str(&content) {
         Ok(cache_data) => {
-            println!("Successfully parsed cache file");
+            debug!("Successfully parsed cache file");
             Ok(Some(cache_data))
         },
         Err(e) => {
-            println!("Failed to parse cache file: {}", e);
+            warn!("Failed to parse cache file: {}", e);
             
             
-            println!("Removing corrupted cache file");
+            debug!("Removing corrupted cache file");
             let _ = fs::remove_file(cache_file);
             
             
@@ -58,79 +221,511 @@ pub fn read_cache(cache_file: &Path) -> Result<Option<CacheData>, WallbashError>
     }
 }
 
-/// Writes the palette cache to a file in JSON format.
-pub fn write_cache(cache_file: &Path, data: &CacheData) -> Result<(), WallbashError> {
-    println!("Writing cache to: {}", cache_file.display());
+/// Writes a palette cache entry in JSON format.
+fn write_cache(cache_file: &Path, data: &CacheData) -> Result<(), WallbashError> {
+    debug!("Writing cache to: {}", cache_file.display());
     
     let json_string = serde_json::to_string_pretty(data)?;
-    println!("Cache data size: {} bytes", json_string.len());
-    
-    if let Some(parent) = cache_file.parent() {
-        fs::create_dir_all(parent)?;
-    }
+    debug!("Cache data size: {} bytes", json_string.len());
     
-    let mut file = File::create(cache_file)?;
-    file.write_all(json_string.as_bytes())?;
-    println!("Cache written successfully");
+    atomic::write_file(cache_file, json_string.as_bytes())?;
+    debug!("Cache written successfully");
     Ok(())
 }
 
-/// Checks if the palette cache is valid for the current image and settings, returning the cached palette if valid.
+/// Looks up the cached palette for the image and settings, returning None if it must be regenerated.
+///
+/// A hit marks the entry as recently used, except with `dry_run`.
 pub fn needs_regeneration(
-    cache_file: &Path,
+    cache_dir: &Path,
     current_image_path: &Path,
-    current_profile: &ColorProfile,
-    current_sort_mode: SortMode,
-    is_wallset: bool,
+    current_image: &ImageChecksum,
+    current_settings: &ExtractionSettings,
+    dry_run: bool,
 ) -> Result<Option<Palette>, WallbashError> {
-    let image_path_str = current_image_path.display().to_string();
+    let entry = entry_path(cache_dir, &current_image.checksum, current_settings)?;
 
-    match read_cache(cache_file)? {
-        Some(cached_data) => {
-            if cached_data.image_path != image_path_str {
-                println!("Cache invalidated: Image path changed.");
-                return Ok(None);
+    match read_cache(&entry)? {
+        Some(mut cached_data) => {
+            if !dry_run {
+                touch(&entry);
             }
-
-            if &cached_data.color_profile != current_profile
-                || cached_data.sort_mode != current_sort_mode
-                || cached_data.wallset != is_wallset
-            {
-                println!("Cache invalidated: Profile, sort mode, or extraction method changed.");
-                return Ok(None);
-            }
-
-            let current_checksum = calculate_checksum(current_image_path)?;
-            if cached_data.image_checksum != current_checksum {
-                println!("Cache invalidated: Image content changed (checksum mismatch).");
-                return Ok(None);
-            }
-
-            println!("Using cached palette for '{}'", image_path_str);
+            let image_path_str = current_image_path.display().to_string();
+            info!("Using cached palette for '{}'", image_path_str);
+            cached_data.palette.wallpaper = image_path_str;
             Ok(Some(cached_data.palette))
         }
         None => {
-            println!("No valid cache found.");
+            info!("No cached palette for this image and settings.");
             Ok(None)
         }
     }
 }
 
-/// Creates a new CacheData struct for the current palette and settings.
-pub fn create_cache_data(
+/// Stores a palette in the cache, then evicts the least recently used entries beyond the configured limits.
+pub fn store_palette(
+    cache_dir: &Path,
     image_path: &Path,
-    profile: &ColorProfile,
-    sort_mode: SortMode,
+    image: &ImageChecksum,
+    settings: &ExtractionSettings,
     palette: &Palette,
-    is_wallset: bool,
-) -> Result<CacheData, WallbashError> {
-    let checksum = calculate_checksum(image_path)?;
-    Ok(CacheData {
-        image_path: image_path.display().to_string(),
-        image_checksum: checksum,
-        color_profile: profile.clone(),
-        sort_mode,
+    limits: &CacheConfig,
+) -> Result<(), WallbashError> {
+    let entry = entry_path(cache_dir, &image.checksum, settings)?;
+    let data = CacheData {
+        image_path: canonical_path(image_path),
+        image_checksum: image.checksum.clone(),
+        image_stamp: Some(image.stamp.clone()),
+        settings: settings.clone(),
         palette: palette.clone(),
-        wallset: is_wallset,
-    })
+    };
+    write_cache(&entry, &data)?;
+    evict(cache_dir, limits, Some(&entry))?;
+    Ok(())
+}
+
+/// Removes the least recently used entries until the cache fits the configured entry count and size.
+///
+/// The `current` entry, the one just written, is always kept even if it alone exceeds the limits.
+fn evict(
+    cache_dir: &Path,
+    limits: &CacheConfig,
+    current: Option<&Path>,
+) -> Result<(), WallbashError> {
+    let max_entries = limits.max_entries();
+    let max_bytes = limits.max_bytes();
+    let mut kept = 0;
+    let mut total_bytes = 0;
+    for entry in entry_files(cache_dir)? {
+        let is_current = current == Some(entry.path.as_path());
+        if is_current || (kept < max_entries && total_bytes + entry.size <= max_bytes) {
+            kept += 1;
+            total_bytes += entry.size;
+            continue;
+        }
+        fs::remove_file(&entry.path)?;
+        debug!("Evicted cache entry {}", entry.path.display());
+    }
+    Ok(())
+}
+
+/// Returns the most recently used cached palette, if any.
+pub fn latest_palette(cache_dir: &Path) -> Result<Option<Palette>, WallbashError> {
+    for entry in entry_files(cache_dir)? {
+        if let Some(data) = read_cache(&entry.path)? {
+            return Ok(Some(data.palette));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the number of files in `dir` and their total size in bytes.
+fn dir_usage(dir: &Path) -> Result<(usize, u64), WallbashError> {
+    if !dir.is_dir() {
+        return Ok((0, 0));
+    }
+    let mut count = 0;
+    let mut bytes = 0;
+    for entry in fs::read_dir(dir)? {
+        let metadata = entry?.metadata()?;
+        if metadata.is_file() {
+            count += 1;
+            bytes += metadata.len();
+        }
+    }
+    Ok((count, bytes))
+}
+
+/// Prints the cached palettes, most recently used first, and the thumbnails and dcol files generated in wallset mode.
+pub fn list_cache(paths: &AppPaths) -> Result<(), WallbashError> {
+    println!("Cache directory: {}", paths.cache_dir.display());
+    let entries = entry_files(&paths.palette_cache_dir)?;
+    if entries.is_empty() {
+        println!("No cached palettes.");
+    }
+    for entry in &entries {
+        let Ok(content) = fs::read_to_string(&entry.path) else {
+            continue;
+        };
+        let Ok(data) = serde_json::from_str::<CacheData>(&content) else {
+            println!("{} (unreadable)", entry.path.display());
+            continue;
+        };
+        let age = SystemTime::now()
+            .duration_since(entry.last_used)
+            .unwrap_or_default()
+            .as_secs();
+        println!("{} (last used {} min ago)", data.image_path, age / 60);
+        println!(
+            "  Profile: {}, Mode: {}, Colors: {}, Fuzz: {}, Backend: {} {}, Color space: {}, Min contrast: {}, Wallset: {}",
+            data.settings.color_profile,
+            data.settings.sort_mode,
+            data.settings.colors,
+            data.settings.fuzz,
+            data.settings.backend,
+            data.settings.backend_version,
+            data.settings.colorspace,
+            data.settings.min_contrast,
+            data.settings.wallset
+        );
+        println!("  Primary: {}", data.palette.primary.join(" "));
+    }
+
+    let (palettes, palettes_bytes) = dir_usage(&paths.palette_cache_dir)?;
+    let (thumbs, thumbs_bytes) = dir_usage(&paths.thumbs_dir)?;
+    let (dcols, dcols_bytes) = dir_usage(&paths.dcols_dir)?;
+    println!("Palettes: {} ({} bytes)", palettes, palettes_bytes);
+    println!("Thumbnails: {} ({} bytes)", thumbs, thumbs_bytes);
+    println!("Dcol files: {} ({} bytes)", dcols, dcols_bytes);
+    Ok(())
+}
+
+/// Removes all cached palettes, the wallset thumbnails, and the hashed dcol files.
+pub fn clear_cache(paths: &AppPaths) -> Result<(), WallbashError> {
+    for file in [
+        &paths.cache_dir.join(LEGACY_CACHE_FILE),
+        &paths.mpc_cache_file,
+        &paths.checksum_index_file,
+    ] {
+        if file.exists() {
+            fs::remove_file(file)?;
+            info!("Removed {}", file.display());
+        }
+    }
+    for dir in [
+        &paths.palette_cache_dir,
+        &paths.thumbs_dir,
+        &paths.dcols_dir,
+    ] {
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+            info!("Removed {}", dir.display());
+        }
+    }
+    info!("Cache cleared.");
+    Ok(())
+}
+
+/// Removes cached palettes whose source image no longer exists or has changed, then applies the size limits.
+///
+/// Images whose size, mtime, and inode match the entry are not re-hashed unless `strict_hash` is set.
+pub fn prune_cache(paths: &AppPaths, limits: &CacheConfig) -> Result<(), WallbashError> {
+    let strict = limits.strict_hash.unwrap_or(false);
+    let mut removed = 0;
+    for entry in entry_files(&paths.palette_cache_dir)? {
+        let Some(data) = read_cache(&entry.path)? else {
+            continue;
+        };
+        let image_path = Path::new(&data.image_path);
+        let stale = if !image_path.is_file() {
+            info!("Cached image no longer exists: {}", data.image_path);
+            true
+        } else if !strict && data.image_stamp == Some(FileStamp::of(image_path)?) {
+            false
+        } else if calculate_checksum(image_path)? != data.image_checksum {
+            info!("Cached image has changed: {}", data.image_path);
+            true
+        } else {
+            false
+        };
+
+        if stale {
+            fs::remove_file(&entry.path)?;
+            removed += 1;
+        }
+    }
+    evict(&paths.palette_cache_dir, limits, None)?;
+
+    let mut index = read_index(&paths.checksum_index_file);
+    index.retain(|path, _| Path::new(path).is_file());
+    write_index(&paths.checksum_index_file, &index)?;
+
+    info!("Pruned {} stale cache entries.", removed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::BackendKind;
+    use crate::config::{ColorProfile, ColorSpace, SortMode};
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("wallrust-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn settings(colors: usize) -> ExtractionSettings {
+        ExtractionSettings {
+            color_profile: ColorProfile::Default,
+            sort_mode: SortMode::Auto,
+            wallset: false,
+            backend: BackendKind::Native,
+            colorspace: ColorSpace::Hsb,
+            min_contrast: 4.5,
+            colors,
+            fuzz: 70,
+            backend_version: "1".to_string(),
+        }
+    }
+
+    fn palette(primary: &str) -> Palette {
+        Palette {
+            primary: vec![primary.to_string()],
+            ..Palette::default()
+        }
+    }
+
+    fn paths(dir: &Path) -> AppPaths {
+        AppPaths {
+            template_dir: dir.join("templates"),
+            output_dir: dir.join("out"),
+            cache_dir: dir.join("cache"),
+            thumbs_dir: dir.join("cache/thumbs"),
+            dcols_dir: dir.join("cache/dcols"),
+            mpc_cache_file: dir.join("cache/wallbash.mpc"),
+            palette_cache_dir: dir.join("cache/palettes"),
+            checksum_index_file: dir.join("cache/checksums.json"),
+            sequences_file: dir.join("cache/sequences"),
+            pywal_cache_dir: dir.join("wal"),
+        }
+    }
+
+    /// Writes an image file and caches a palette for it, returning the cache entry.
+    fn store(paths: &AppPaths, image: &Path, contents: &[u8], colors: usize) -> PathBuf {
+        fs::write(image, contents).unwrap();
+        let checksum = image_checksum(&paths.checksum_index_file, image, false, false).unwrap();
+        let settings = settings(colors);
+        store_palette(
+            &paths.palette_cache_dir,
+            image,
+            &checksum,
+            &settings,
+            &palette("112233"),
+            &CacheConfig::default(),
+        )
+        .unwrap();
+        entry_path(&paths.palette_cache_dir, &checksum.checksum, &settings).unwrap()
+    }
+
+    fn set_age(path: &Path, secs: u64) {
+        let file = File::options().append(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    fn cached(cache_dir: &Path) -> Vec<PathBuf> {
+        let mut entries: Vec<PathBuf> = entry_files(cache_dir)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn settings_are_part_of_the_key() {
+        let dir = temp_dir("key");
+        let paths = paths(&dir);
+        let image = dir.join("wall.png");
+        fs::write(&image, b"image").unwrap();
+        let checksum = image_checksum(&paths.checksum_index_file, &image, false, false).unwrap();
+        let limits = CacheConfig::default();
+        for (colors, primary) in [(4, "111111"), (6, "222222")] {
+            store_palette(
+                &paths.palette_cache_dir,
+                &image,
+                &checksum,
+                &settings(colors),
+                &palette(primary),
+                &limits,
+            )
+            .unwrap();
+        }
+
+        assert_eq!(cached(&paths.palette_cache_dir).len(), 2);
+        for (colors, primary) in [(4, "111111"), (6, "222222")] {
+            let hit = needs_regeneration(
+                &paths.palette_cache_dir,
+                &image,
+                &checksum,
+                &settings(colors),
+                true,
+            )
+            .unwrap()
+            .unwrap();
+            assert_eq!(hit.primary, vec![primary]);
+        }
+        let miss = needs_regeneration(
+            &paths.palette_cache_dir,
+            &image,
+            &checksum,
+            &settings(8),
+            true,
+        )
+        .unwrap();
+        assert!(miss.is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn entries_store_the_canonical_image_path() {
+        let dir = temp_dir("canonical");
+        let paths = paths(&dir);
+        let image = dir.join("wall.png");
+        let entry = store(&paths, &dir.join(".").join("wall.png"), b"image", 4);
+
+        let data = read_cache(&entry).unwrap().unwrap();
+        assert_eq!(
+            Path::new(&data.image_path),
+            fs::canonicalize(&image).unwrap()
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn evict_removes_the_least_recently_used_entries_first() {
+        let dir = temp_dir("evict-lru");
+        let paths = paths(&dir);
+        let entries: Vec<PathBuf> = (0..3)
+            .map(|i| {
+                store(
+                    &paths,
+                    &dir.join(format!("wall{}.png", i)),
+                    format!("image {}", i).as_bytes(),
+                    4,
+                )
+            })
+            .collect();
+        // wall0 is the oldest and wall2 the most recently used.
+        for (entry, age) in entries.iter().zip([300, 200, 100]) {
+            set_age(entry, age);
+        }
+
+        let limits = CacheConfig {
+            max_entries: Some(2),
+            ..CacheConfig::default()
+        };
+        evict(&paths.palette_cache_dir, &limits, None).unwrap();
+        let mut expected = vec![entries[1].clone(), entries[2].clone()];
+        expected.sort();
+        assert_eq!(cached(&paths.palette_cache_dir), expected);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn evict_never_removes_the_current_entry() {
+        let dir = temp_dir("evict-current");
+        let paths = paths(&dir);
+        let current = store(&paths, &dir.join("old.png"), b"old image", 4);
+        let newer = store(&paths, &dir.join("new.png"), b"new image", 4);
+        set_age(&current, 100);
+
+        for limits in [
+            CacheConfig {
+                max_entries: Some(0),
+                ..CacheConfig::default()
+            },
+            CacheConfig {
+                max_size_mb: Some(0),
+                ..CacheConfig::default()
+            },
+        ] {
+            evict(&paths.palette_cache_dir, &limits, Some(&current)).unwrap();
+            assert_eq!(cached(&paths.palette_cache_dir), vec![current.clone()]);
+        }
+        assert!(!newer.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn prune_drops_only_entries_for_missing_or_changed_images() {
+        let dir = temp_dir("prune");
+        let paths = paths(&dir);
+        let kept = store(&paths, &dir.join("kept.png"), b"kept image", 4);
+        let deleted = store(&paths, &dir.join("deleted.png"), b"deleted image", 4);
+        let changed = store(&paths, &dir.join("changed.png"), b"changed image", 4);
+        fs::remove_file(dir.join("deleted.png")).unwrap();
+        fs::write(dir.join("changed.png"), b"edited image").unwrap();
+
+        prune_cache(&paths, &CacheConfig::default()).unwrap();
+        assert_eq!(cached(&paths.palette_cache_dir), vec![kept]);
+        assert!(!deleted.exists() && !changed.exists());
+
+        let index = read_index(&paths.checksum_index_file);
+        assert!(index.contains_key(&canonical_path(&dir.join("kept.png"))));
+        assert!(!index.contains_key(&canonical_path(&dir.join("deleted.png"))));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Writes an image and indexes it under a made-up checksum, so a returned fake proves the image was not hashed.
+    fn index_with_fake_checksum(dir: &Path) -> (PathBuf, PathBuf) {
+        let image = dir.join("wall.png");
+        let index_file = dir.join("checksums.json");
+        fs::write(&image, b"image").unwrap();
+        let mut index = HashMap::new();
+        index.insert(
+            canonical_path(&image),
+            IndexedChecksum {
+                stamp: FileStamp::of(&image).unwrap(),
+                checksum: "fake".to_string(),
+            },
+        );
+        write_index(&index_file, &index).unwrap();
+        (image, index_file)
+    }
+
+    #[test]
+    fn unchanged_stamp_skips_hashing() {
+        let dir = temp_dir("checksum-unchanged");
+        let (image, index_file) = index_with_fake_checksum(&dir);
+        let checksum = image_checksum(&index_file, &image, false, false).unwrap();
+        assert_eq!(checksum.checksum, "fake");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn changed_mtime_or_size_rehashes() {
+        let dir = temp_dir("checksum-changed");
+        let (image, index_file) = index_with_fake_checksum(&dir);
+        set_age(&image, 100);
+        let checksum = image_checksum(&index_file, &image, false, false).unwrap();
+        assert_eq!(checksum.checksum, calculate_checksum(&image).unwrap());
+
+        let (image, index_file) = index_with_fake_checksum(&dir);
+        fs::write(&image, b"a larger image").unwrap();
+        let checksum = image_checksum(&index_file, &image, false, false).unwrap();
+        assert_eq!(checksum.checksum, calculate_checksum(&image).unwrap());
+        assert_eq!(
+            read_index(&index_file)[&canonical_path(&image)].checksum,
+            checksum.checksum
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn strict_mode_always_hashes() {
+        let dir = temp_dir("checksum-strict");
+        let (image, index_file) = index_with_fake_checksum(&dir);
+        let checksum = image_checksum(&index_file, &image, true, false).unwrap();
+        assert_eq!(checksum.checksum, calculate_checksum(&image).unwrap());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn dry_run_does_not_write_the_index() {
+        let dir = temp_dir("checksum-dry-run");
+        let image = dir.join("wall.png");
+        let index_file = dir.join("checksums.json");
+        fs::write(&image, b"image").unwrap();
+        let checksum = image_checksum(&index_file, &image, false, true).unwrap();
+        assert_eq!(checksum.checksum, calculate_checksum(&image).unwrap());
+        assert!(!index_file.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
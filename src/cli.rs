@@ -1,22 +1,288 @@
 //! Defines and parses command-line arguments for Wallrust using clap.
 //!
-//! This module provides the `Cli` struct, which specifies all supported options, flags, and arguments for the Wallrust CLI interface.
-use crate::config::{DEFAULT_COLORS, DEFAULT_FUZZ};
-use clap::Parser;
+//! This module provides the `Cli` struct, which specifies all supported options, flags, and arguments for the Wallrust CLI interface. Running without a subcommand performs the full extraction and output pipeline; subcommands expose its individual steps.
+use crate::backend::BackendKind;
+use crate::config::{
+    ColorSpace, DEFAULT_COLORS, DEFAULT_FUZZ, DEFAULT_MIN_CONTRAST, DEFAULT_WATCH_DEBOUNCE_MS,
+    DEFAULT_WATCH_INTERVAL_MS, UserConfig,
+};
+use crate::output::PrintFormat;
+use crate::preview::ColorMode;
+use crate::swatches::SwatchFormat;
+use crate::writers::Emitter;
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Args, Parser, Subcommand};
 
 /// Command-line arguments for Wallrust.
 ///
 /// This struct defines all supported CLI options, flags, and arguments, and is parsed using `clap`.
 #[derive(Parser, Debug)]
-#[command(author, version, about = "Generates color palettes from wallpapers", long_about = None)]
+#[command(
+    author,
+    version,
+    about = "Generates color palettes from wallpapers",
+    long_about = None,
+    args_conflicts_with_subcommands = true
+)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub extract: ExtractArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    #[arg(short, long, value_name = "DIR", global = true)]
+    pub output_dir: Option<String>,
+
+    #[arg(
+        long,
+        overrides_with = "no_html",
+        help = "Generate HTML color palette preview"
+    )]
+    pub html: bool,
+
+    #[arg(
+        long,
+        overrides_with = "html",
+        help = "Don't generate the HTML preview, even if the config enables it"
+    )]
+    pub no_html: bool,
+
+    #[arg(
+        long,
+        overrides_with = "templates",
+        help = "Skip custom template generation"
+    )]
+    pub no_templates: bool,
+
+    #[arg(
+        long,
+        overrides_with = "no_templates",
+        help = "Generate custom templates, even if the config skips them"
+    )]
+    pub templates: bool,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["input_image", "detect_hyprland", "from_dcol", "from_base16"],
+        help = "Write outputs from a palette JSON file instead of extracting from an image"
+    )]
+    pub from_palette: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["input_image", "detect_hyprland", "from_base16"],
+        help = "Write outputs from a dcol file instead of extracting from an image"
+    )]
+    pub from_dcol: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["input_image", "detect_hyprland"],
+        help = "Write outputs from a Base16 or Base24 YAML scheme instead of extracting from an image"
+    )]
+    pub from_base16: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        global = true,
+        help = "Read defaults from this config file instead of ~/.config/wallrust/config.toml"
+    )]
+    pub config: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        conflicts_with = "verbose",
+        help = "Only log errors, keeping stdout for machine-readable output"
+    )]
+    pub quiet: bool,
+
+    #[arg(
+        long,
+        global = true,
+        action = ArgAction::Count,
+        help = "Log more details on stderr (--verbose --verbose for everything)"
+    )]
+    pub verbose: u8,
+}
+
+/// Subcommands exposing the individual steps of the default pipeline.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Extract a palette from an image and write only wallrust.json
+    Extract {
+        #[command(flatten)]
+        extract: ExtractArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+    /// Apply templates and write outputs from an existing palette JSON file
+    Render {
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Palette JSON file (as written by wallrust)"
+        )]
+        palette: String,
+
+        #[arg(
+            long,
+            overrides_with = "no_html",
+            help = "Generate HTML color palette preview"
+        )]
+        html: bool,
+
+        #[arg(
+            long,
+            overrides_with = "html",
+            help = "Don't generate the HTML preview, even if the config enables it"
+        )]
+        no_html: bool,
+
+        #[arg(
+            long,
+            overrides_with = "templates",
+            help = "Skip custom template generation"
+        )]
+        no_templates: bool,
+
+        #[arg(
+            long,
+            overrides_with = "no_templates",
+            help = "Generate custom templates, even if the config skips them"
+        )]
+        templates: bool,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Inspect or clean the palette cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Show a palette in the terminal or as an HTML page
+    Preview {
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Palette JSON file to preview [default: the cached palette]"
+        )]
+        palette: Option<String>,
+
+        #[arg(
+            long,
+            help = "Write palette.html to the output directory instead of printing"
+        )]
+        html: bool,
+
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = ColorMode::Auto,
+            value_name = "WHEN",
+            help = "How to draw swatches: truecolor, 256-color fallback, or plain hex"
+        )]
+        color: ColorMode,
+    },
+    /// Print the current Hyprland wallpaper path
+    Detect,
+    /// Re-theme whenever the given wallpaper file or symlink, or the Hyprland wallpaper, changes
+    Watch {
+        #[command(flatten)]
+        extract: ExtractArgs,
+
+        #[arg(
+            long,
+            default_value_t = DEFAULT_WATCH_INTERVAL_MS,
+            value_name = "MS",
+            help = "How often to check the wallpaper for changes"
+        )]
+        interval: u64,
+
+        #[arg(
+            long,
+            default_value_t = DEFAULT_WATCH_DEBOUNCE_MS,
+            value_name = "MS",
+            help = "How long a change must be stable before re-theming"
+        )]
+        debounce: u64,
+
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[arg(
+            long,
+            overrides_with = "no_html",
+            help = "Generate HTML color palette preview"
+        )]
+        html: bool,
+
+        #[arg(
+            long,
+            overrides_with = "html",
+            help = "Don't generate the HTML preview, even if the config enables it"
+        )]
+        no_html: bool,
+
+        #[arg(
+            long,
+            overrides_with = "templates",
+            help = "Skip custom template generation"
+        )]
+        no_templates: bool,
+
+        #[arg(
+            long,
+            overrides_with = "no_templates",
+            help = "Generate custom templates, even if the config skips them"
+        )]
+        templates: bool,
+    },
+}
+
+/// Actions of the `cache` subcommand.
+#[derive(Subcommand, Debug, Clone, Copy)]
+pub enum CacheAction {
+    /// List cached palettes and generated files
+    List,
+    /// Remove all cached palettes, thumbnails, and dcol files
+    Clear,
+    /// Remove cached palettes whose source image is gone or has changed, then apply the size limits
+    Prune,
+}
+
+/// Options controlling how a palette is extracted from an image.
+#[derive(Args, Debug, Clone)]
+pub struct ExtractArgs {
     pub input_image: Option<String>,
 
     #[arg(short, long)]
     pub force: bool,
 
-    #[arg(short, long, value_name = "DIR")]
-    pub output_dir: Option<String>,
+    #[arg(
+        long,
+        overrides_with = "no_strict_hash",
+        help = "Always hash the image instead of trusting unchanged size, mtime, and inode"
+    )]
+    pub strict_hash: bool,
+
+    #[arg(
+        long,
+        overrides_with = "strict_hash",
+        help = "Trust unchanged size, mtime, and inode, even if the config enables strict hashing"
+    )]
+    pub no_strict_hash: bool,
 
     #[arg(short, long, help = "Use vibrant color profile")]
     pub vibrant: bool,
@@ -35,6 +301,13 @@ pub struct Cli {
     )]
     pub custom: Option<String>,
 
+    #[arg(
+        long,
+        help = "Use a named color profile from the config file or a built-in one (default, vibrant, pastel, mono)",
+        value_name = "NAME"
+    )]
+    pub profile: Option<String>,
+
     
     #[arg(short, long, help = "Force dark sort mode")]
     pub dark: bool,
@@ -42,6 +315,13 @@ pub struct Cli {
     #[arg(short, long, help = "Force light sort mode")]
     pub light: bool,
 
+    #[arg(
+        long,
+        conflicts_with_all = ["dark", "light"],
+        help = "Pick dark or light sort mode from the image, overriding the config's mode"
+    )]
+    pub auto: bool,
+
     
     #[arg(long, default_value_t = DEFAULT_COLORS, help = "Number of primary colors to extract")]
     pub colors: usize,
@@ -56,12 +336,332 @@ pub struct Cli {
     )]
     pub detect_hyprland: bool,
 
-    #[arg(long, help = "Generate HTML color palette preview")]
-    pub html: bool,
-    
-    #[arg(long, help = "Generate thumbnails and dcol files compatible with wallbash scripts")]
+    #[arg(
+        long,
+        overrides_with = "no_wallset",
+        help = "Generate thumbnails and dcol files compatible with wallbash scripts"
+    )]
     pub wallset: bool,
 
-    #[arg(long, help = "Skip custom template generation")]
-    pub no_templates: bool,
+    #[arg(
+        long,
+        overrides_with = "wallset",
+        help = "Don't use wallset mode, even if the config enables it"
+    )]
+    pub no_wallset: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = BackendKind::Magick,
+        help = "Color extraction backend"
+    )]
+    pub backend: BackendKind,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ColorSpace::Hsb,
+        help = "Color space for sorting and accent/text derivation"
+    )]
+    pub colorspace: ColorSpace,
+
+    #[arg(
+        long,
+        default_value_t = DEFAULT_MIN_CONTRAST,
+        value_name = "RATIO",
+        help = "Minimum WCAG contrast ratio of each text color against its primary (0 disables)"
+    )]
+    pub min_contrast: f64,
+}
+
+/// Options controlling whether a run writes files and what it prints to stdout.
+#[derive(Args, Debug, Clone)]
+pub struct WriteArgs {
+    #[arg(
+        long,
+        help = "Show each output that would be created or modified, with a diff on stderr, without writing any file or the cache"
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        help = "Print the palette to stdout in this format"
+    )]
+    pub print: Option<PrintFormat>,
+
+    #[arg(
+        long,
+        help = "Don't write output files, templates, or the HTML preview, and run no hooks"
+    )]
+    pub no_write: bool,
+}
+
+/// Options controlling what a run writes, prints, or applies besides the output files.
+#[derive(Args, Debug, Clone)]
+pub struct OutputArgs {
+    #[command(flatten)]
+    pub write: WriteArgs,
+
+    #[arg(
+        long,
+        conflicts_with = "no_write",
+        help = "Recolor open terminals via escape sequences and save them to the cache for new shells"
+    )]
+    pub apply_terminals: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "APPS",
+        help = "Also write built-in color configs for these apps (a same-named template overrides one)"
+    )]
+    pub emit: Vec<Emitter>,
+
+    #[arg(
+        long,
+        help = "Also write pywal's colors.json, colors, colors.sh, and colors.Xresources to ~/.cache/wal"
+    )]
+    pub pywal_compat: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "FORMATS",
+        help = "Also write the palette as swatches for design tools (wallrust.gpl, .ase, .aco)"
+    )]
+    pub swatches: Vec<SwatchFormat>,
+}
+
+impl Cli {
+    /// Fills in every option not given on the command line from the user config.
+    pub fn apply_config(&mut self, config: &UserConfig, matches: &ArgMatches) {
+        if self.output_dir.is_none() {
+            self.output_dir = config.output_dir.clone();
+        }
+
+        match &mut self.command {
+            None => {
+                apply_flag(&mut self.html, config.html, matches, "html", "no_html");
+                apply_flag(
+                    &mut self.no_templates,
+                    config.no_templates,
+                    matches,
+                    "no_templates",
+                    "templates",
+                );
+                self.extract.apply_config(config, matches);
+            }
+            Some(Command::Extract { extract, .. }) => {
+                if let Some(sub_matches) = matches.subcommand_matches("extract") {
+                    extract.apply_config(config, sub_matches);
+                }
+            }
+            Some(Command::Watch {
+                extract,
+                html,
+                no_templates,
+                ..
+            }) => {
+                if let Some(sub_matches) = matches.subcommand_matches("watch") {
+                    apply_flag(html, config.html, sub_matches, "html", "no_html");
+                    apply_flag(
+                        no_templates,
+                        config.no_templates,
+                        sub_matches,
+                        "no_templates",
+                        "templates",
+                    );
+                    extract.apply_config(config, sub_matches);
+                }
+            }
+            Some(Command::Render {
+                html, no_templates, ..
+            }) => {
+                if let Some(sub_matches) = matches.subcommand_matches("render") {
+                    apply_flag(html, config.html, sub_matches, "html", "no_html");
+                    apply_flag(
+                        no_templates,
+                        config.no_templates,
+                        sub_matches,
+                        "no_templates",
+                        "templates",
+                    );
+                }
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Takes a boolean option from the config unless it or its negation was given on the command line.
+fn apply_flag(
+    flag: &mut bool,
+    config_value: Option<bool>,
+    matches: &ArgMatches,
+    id: &str,
+    negation: &str,
+) {
+    let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    if let Some(value) = config_value.filter(|_| !from_cli(id) && !from_cli(negation)) {
+        *flag = value;
+    }
+}
+
+impl ExtractArgs {
+    /// Fills in every extraction option not given on the command line from the user config.
+    fn apply_config(&mut self, config: &UserConfig, matches: &ArgMatches) {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        if let Some(colors) = config.colors.filter(|_| !from_cli("colors")) {
+            self.colors = colors;
+        }
+        if let Some(fuzz) = config.fuzz.filter(|_| !from_cli("fuzz")) {
+            self.fuzz = fuzz;
+        }
+        if let Some(backend) = config.backend.filter(|_| !from_cli("backend")) {
+            self.backend = backend;
+        }
+        if let Some(colorspace) = config.colorspace.filter(|_| !from_cli("colorspace")) {
+            self.colorspace = colorspace;
+        }
+        if let Some(min_contrast) = config.min_contrast.filter(|_| !from_cli("min_contrast")) {
+            self.min_contrast = min_contrast;
+        }
+        apply_flag(
+            &mut self.wallset,
+            config.wallset,
+            matches,
+            "wallset",
+            "no_wallset",
+        );
+        apply_flag(
+            &mut self.strict_hash,
+            config.cache.strict_hash,
+            matches,
+            "strict_hash",
+            "no_strict_hash",
+        );
+    }
+
+    /// Returns true if any color profile flag was given.
+    pub fn has_profile_flag(&self) -> bool {
+        self.vibrant || self.pastel || self.mono || self.custom.is_some() || self.profile.is_some()
+    }
+
+    /// Returns true if a sort mode flag was given.
+    pub fn has_mode_flag(&self) -> bool {
+        self.dark || self.light || self.auto
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    /// Parses `args` like `main` does and fills in the options they leave unset from `config` (TOML).
+    fn parse(args: &[&str], config: &str) -> Cli {
+        let config: UserConfig = toml::from_str(config).unwrap();
+        let matches = Cli::command()
+            .try_get_matches_from(std::iter::once("wallrust").chain(args.iter().copied()))
+            .unwrap();
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        cli.apply_config(&config, &matches);
+        cli
+    }
+
+    const ENABLING_CONFIG: &str = r#"
+        html = true
+        wallset = true
+        no_templates = true
+        colors = 6
+        [cache]
+        strict_hash = true
+    "#;
+
+    const DISABLING_CONFIG: &str = r#"
+        html = false
+        wallset = false
+        no_templates = false
+        [cache]
+        strict_hash = false
+    "#;
+
+    #[test]
+    fn config_fills_options_not_given() {
+        let cli = parse(&["wall.png"], ENABLING_CONFIG);
+        assert!(cli.html && cli.no_templates);
+        assert!(cli.extract.wallset && cli.extract.strict_hash);
+        assert_eq!(cli.extract.colors, 6);
+
+        let cli = parse(&["wall.png"], "");
+        assert!(!cli.html && !cli.no_templates);
+        assert!(!cli.extract.wallset && !cli.extract.strict_hash);
+        assert_eq!(cli.extract.colors, DEFAULT_COLORS);
+    }
+
+    #[test]
+    fn negated_flags_override_config() {
+        let cli = parse(
+            &[
+                "wall.png",
+                "--no-html",
+                "--templates",
+                "--no-wallset",
+                "--no-strict-hash",
+                "--colors",
+                "4",
+            ],
+            ENABLING_CONFIG,
+        );
+        assert!(!cli.html && !cli.no_templates);
+        assert!(!cli.extract.wallset && !cli.extract.strict_hash);
+        assert_eq!(cli.extract.colors, 4);
+    }
+
+    #[test]
+    fn flags_override_disabling_config() {
+        let cli = parse(
+            &[
+                "wall.png",
+                "--html",
+                "--no-templates",
+                "--wallset",
+                "--strict-hash",
+            ],
+            DISABLING_CONFIG,
+        );
+        assert!(cli.html && cli.no_templates);
+        assert!(cli.extract.wallset && cli.extract.strict_hash);
+    }
+
+    #[test]
+    fn last_of_a_flag_and_its_negation_wins() {
+        let cli = parse(&["wall.png", "--html", "--no-html"], "");
+        assert!(!cli.html);
+        let cli = parse(&["wall.png", "--no-html", "--html"], "");
+        assert!(cli.html);
+    }
+
+    #[test]
+    fn subcommand_flags_override_config() {
+        let html = |cli: Cli| match cli.command {
+            Some(Command::Render {
+                html, no_templates, ..
+            }) => (html, no_templates),
+            other => panic!("parsed {:?}", other),
+        };
+        let render = ["render", "--palette", "wallrust.json"];
+        assert_eq!(html(parse(&render, ENABLING_CONFIG)), (true, true));
+        let cli = parse(
+            &[&render[..], &["--no-html", "--templates"]].concat(),
+            ENABLING_CONFIG,
+        );
+        assert_eq!(html(cli), (false, false));
+    }
 }
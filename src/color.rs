@@ -0,0 +1,551 @@
+//! Pure color math used to derive text and accent colors from extracted primaries.
+//!
+//! This module provides hex parsing, HSL/HSB and OKLab/OKLCH conversion, normalization, modulation, brightness checks, and WCAG contrast enforcement. The formulas mirror ImageMagick's so that palettes match the ones previously produced by running `magick` once per color. Channels are expressed as `[r, g, b]` in the 0..1 range.
+use crate::error::WallbashError;
+
+/// Parses a 6-digit hex color (with or without a leading `#`) into RGB channels.
+pub fn parse_hex(hex_color: &str) -> Result<[f64; 3], WallbashError> {
+    let hex = hex_color.trim_start_matches('#');
+    if hex.len() != 6 {
+        return Err(WallbashError::InvalidInput(format!(
+            "Invalid hex color format: '{}'",
+            hex_color
+        )));
+    }
+    Ok([
+        u8::from_str_radix(&hex[0..2], 16)? as f64 / 255.0,
+        u8::from_str_radix(&hex[2..4], 16)? as f64 / 255.0,
+        u8::from_str_radix(&hex[4..6], 16)? as f64 / 255.0,
+    ])
+}
+
+/// Quantizes RGB channels to 8 bits and formats them as an uppercase hex string without `#`.
+pub fn to_hex(rgb: [f64; 3]) -> String {
+    let channel = |v: f64| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "{:02X}{:02X}{:02X}",
+        channel(rgb[0]),
+        channel(rgb[1]),
+        channel(rgb[2])
+    )
+}
+
+/// Rec. 709 luma of gamma-encoded RGB, matching ImageMagick's `-colorspace gray`.
+pub fn gray(rgb: [f64; 3]) -> f64 {
+    0.212656 * rgb[0] + 0.715158 * rgb[1] + 0.072186 * rgb[2]
+}
+
+/// Checks if a hex color is dark, i.e. its gray level is below 50%.
+pub fn is_dark(hex_color: &str) -> Result<bool, WallbashError> {
+    Ok(gray(parse_hex(hex_color)?) < 0.5)
+}
+
+/// WCAG 2.x relative luminance of RGB channels.
+pub fn relative_luminance(rgb: [f64; 3]) -> f64 {
+    let [r, g, b] = rgb.map(|v| {
+        if v <= 0.03928 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+fn luminance_contrast(a: f64, b: f64) -> f64 {
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Returns the WCAG contrast ratio (1 to 21) between two hex colors.
+pub fn contrast_ratio(a_hex: &str, b_hex: &str) -> Result<f64, WallbashError> {
+    Ok(rgb_contrast_ratio(parse_hex(a_hex)?, parse_hex(b_hex)?))
+}
+
+/// Returns the WCAG contrast ratio (1 to 21) between two RGB colors.
+pub fn rgb_contrast_ratio(a: [f64; 3], b: [f64; 3]) -> f64 {
+    luminance_contrast(relative_luminance(a), relative_luminance(b))
+}
+
+/// Converts RGB to (hue, saturation, lightness), all in the 0..1 range.
+pub fn rgb_to_hsl(rgb: [f64; 3]) -> (f64, f64, f64) {
+    let [r, g, b] = rgb;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let c = max - min;
+    let lightness = (max + min) / 2.0;
+    if c <= 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+    let mut hue = if max == r {
+        let h = (g - b) / c;
+        if g < b { h + 6.0 } else { h }
+    } else if max == g {
+        2.0 + (b - r) / c
+    } else {
+        4.0 + (r - g) / c
+    };
+    hue /= 6.0;
+    let saturation = if lightness <= 0.5 {
+        c / (2.0 * lightness)
+    } else {
+        c / (2.0 - 2.0 * lightness)
+    };
+    (hue, saturation, lightness)
+}
+
+/// Converts (hue, saturation, lightness) back to RGB without clamping, as ImageMagick does before quantizing.
+pub fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> [f64; 3] {
+    let c = if lightness <= 0.5 {
+        2.0 * lightness * saturation
+    } else {
+        (2.0 - 2.0 * lightness) * saturation
+    };
+    let min = lightness - 0.5 * c;
+    let mut h = hue * 360.0;
+    h -= 360.0 * (h / 360.0).floor();
+    h /= 60.0;
+    let x = c * (1.0 - (h - 2.0 * (h / 2.0).floor() - 1.0).abs());
+    match h.floor() as i64 {
+        0 => [min + c, min + x, min],
+        1 => [min + x, min + c, min],
+        2 => [min, min + c, min + x],
+        3 => [min, min + x, min + c],
+        4 => [min + x, min, min + c],
+        5 => [min + c, min, min + x],
+        _ => [0.0, 0.0, 0.0],
+    }
+}
+
+/// Converts RGB to (hue in degrees, saturation, brightness), with saturation and brightness in the 0..1 range.
+pub fn rgb_to_hsb(rgb: [f64; 3]) -> (f64, f64, f64) {
+    let [r, g, b] = rgb;
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    if max == 0.0 {
+        return (0.0, 0.0, 0.0);
+    }
+    let saturation = delta / max;
+    if delta == 0.0 {
+        return (0.0, saturation, max);
+    }
+    let mut hue = if r == max {
+        (g - b) / delta
+    } else if g == max {
+        2.0 + (b - r) / delta
+    } else {
+        4.0 + (r - g) / delta
+    };
+    hue /= 6.0;
+    if hue < 0.0 {
+        hue += 1.0;
+    }
+    (hue * 360.0, saturation, max)
+}
+
+/// Converts (hue in degrees, saturation, brightness) to RGB.
+pub fn hsb_to_rgb(hue: f64, saturation: f64, brightness: f64) -> [f64; 3] {
+    if saturation.abs() < f64::EPSILON {
+        return [brightness; 3];
+    }
+    let hue = hue / 360.0;
+    let h = 6.0 * (hue - hue.floor());
+    let f = h - h.floor();
+    let p = brightness * (1.0 - saturation);
+    let q = brightness * (1.0 - saturation * f);
+    let t = brightness * (1.0 - saturation * (1.0 - f));
+    match h as i64 {
+        1 => [q, brightness, p],
+        2 => [p, brightness, t],
+        3 => [p, q, brightness],
+        4 => [t, p, brightness],
+        5 => [brightness, p, q],
+        _ => [brightness, t, p],
+    }
+}
+
+/// Returns the HSB hue of a hex color in degrees.
+pub fn hsb_hue(hex_color: &str) -> Result<f64, WallbashError> {
+    Ok(rgb_to_hsb(parse_hex(hex_color)?).0)
+}
+
+/// Converts an HSB color (hue in degrees, saturation and brightness in percent) to a hex color.
+pub fn color_from_hsb(hue: f64, sat: u8, bri: u8) -> String {
+    to_hex(hsb_to_rgb(
+        hue,
+        f64::from(sat) / 100.0,
+        f64::from(bri) / 100.0,
+    ))
+}
+
+/// Stretches each channel of a set of colors to span the full 0..1 range, like ImageMagick's `-normalize`.
+///
+/// Channels that are constant across the set are left unchanged, so a single solid color normalizes to itself.
+pub fn normalize(colors: &[[f64; 3]]) -> Vec<[f64; 3]> {
+    let mut out = colors.to_vec();
+    for channel in 0..3 {
+        let min = colors.iter().map(|c| c[channel]).fold(f64::MAX, f64::min);
+        let max = colors.iter().map(|c| c[channel]).fold(f64::MIN, f64::max);
+        if max > min {
+            for color in &mut out {
+                color[channel] = (color[channel] - min) / (max - min);
+            }
+        }
+    }
+    out
+}
+
+/// Normalizes and modulates the brightness, saturation, and hue (in percent) of a hex color.
+///
+/// Equivalent to `magick xc:#HEX -normalize -modulate BRI,SAT,HUE`, which modulates in HSL space.
+pub fn modulate(hex_color: &str, bri: u8, sat: u8, hue: u8) -> Result<String, WallbashError> {
+    let rgb = normalize(&[parse_hex(hex_color)?])[0];
+    let (h, s, l) = rgb_to_hsl(rgb);
+    let h = h + ((f64::from(hue) - 100.0) % 200.0) / 200.0;
+    let s = s * 0.01 * f64::from(sat);
+    let l = l * 0.01 * f64::from(bri);
+    Ok(to_hex(hsl_to_rgb(h, s, l)))
+}
+
+fn srgb_to_linear(v: f64) -> f64 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f64) -> f64 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Converts RGB to OKLab (L, a, b).
+pub fn rgb_to_oklab(rgb: [f64; 3]) -> (f64, f64, f64) {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    (
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    )
+}
+
+/// Converts OKLab (L, a, b) to RGB without clamping, so callers can detect out-of-gamut results.
+pub fn oklab_to_rgb(lightness: f64, a: f64, b: f64) -> [f64; 3] {
+    let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+    .map(linear_to_srgb)
+}
+
+/// Converts RGB to OKLCH (lightness, chroma, hue in degrees). Achromatic colors report a hue of 0.
+pub fn rgb_to_oklch(rgb: [f64; 3]) -> (f64, f64, f64) {
+    let (l, a, b) = rgb_to_oklab(rgb);
+    let chroma = (a * a + b * b).sqrt();
+    if chroma < 1e-6 {
+        return (l, 0.0, 0.0);
+    }
+    (l, chroma, b.atan2(a).to_degrees().rem_euclid(360.0))
+}
+
+/// Converts OKLCH to RGB, reducing chroma at constant lightness and hue until the color fits the sRGB gamut.
+pub fn oklch_to_rgb(lightness: f64, chroma: f64, hue: f64) -> [f64; 3] {
+    let lightness = lightness.clamp(0.0, 1.0);
+    let to_rgb = |c: f64| {
+        let h = hue.to_radians();
+        oklab_to_rgb(lightness, c * h.cos(), c * h.sin())
+    };
+    let in_gamut = |rgb: [f64; 3]| rgb.iter().all(|v| (-1e-4..=1.0 + 1e-4).contains(v));
+
+    let rgb = to_rgb(chroma);
+    if in_gamut(rgb) {
+        return rgb;
+    }
+    let (mut low, mut high) = (0.0, chroma);
+    for _ in 0..24 {
+        let mid = (low + high) / 2.0;
+        if in_gamut(to_rgb(mid)) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    to_rgb(low)
+}
+
+/// Returns the perceptual OKLab lightness of a hex color.
+pub fn oklab_lightness(hex_color: &str) -> Result<f64, WallbashError> {
+    Ok(rgb_to_oklab(parse_hex(hex_color)?).0)
+}
+
+/// OKLCH lightness step used while searching for a text color that meets a contrast ratio.
+const CONTRAST_STEP: f64 = 0.01;
+
+/// Adjusts the OKLCH lightness of `text_hex`, keeping its hue and chroma, until it reaches `min_ratio` against `background_hex`.
+///
+/// Lightness moves away from the background first and toward the other extreme only if that fails. Returns the adjusted color and its contrast ratio, or warns and returns the best found if the ratio is unreachable.
+pub fn enforce_contrast(
+    text_hex: &str,
+    background_hex: &str,
+    min_ratio: f64,
+) -> Result<(String, f64), WallbashError> {
+    let background = relative_luminance(parse_hex(background_hex)?);
+    let text = parse_hex(text_hex)?;
+    let initial = luminance_contrast(relative_luminance(text), background);
+    if initial >= min_ratio {
+        return Ok((text_hex.to_string(), initial));
+    }
+
+    let (lightness, chroma, hue) = rgb_to_oklch(text);
+    let directions = if relative_luminance(text) >= background {
+        [1.0, -1.0]
+    } else {
+        [-1.0, 1.0]
+    };
+    let mut best = (text_hex.to_string(), initial);
+    for direction in directions {
+        let mut l = lightness;
+        while (0.0..=1.0).contains(&l) {
+            l += direction * CONTRAST_STEP;
+            let candidate = to_hex(oklch_to_rgb(l, chroma, hue));
+            let ratio = luminance_contrast(relative_luminance(parse_hex(&candidate)?), background);
+            if ratio >= min_ratio {
+                return Ok((candidate, ratio));
+            }
+            if ratio > best.1 {
+                best = (candidate, ratio);
+            }
+        }
+    }
+    log::warn!(
+        "#{} reaches only {:.2}:1 contrast against #{}, below the requested {}:1.",
+        best.0,
+        best.1,
+        background_hex.trim_start_matches('#'),
+        min_ratio
+    );
+    Ok(best)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Primaries from `examples/wallrust.html`, generated by the ImageMagick pipeline, with their
+    /// text colors (`xc:#NEGATED -normalize -modulate BRI,10,100`) and default-curve accents (`xc:hsb(...)`).
+    const MAGICK_PALETTE: [(&str, &str, [&str; 9]); 4] = [
+        (
+            "050910",
+            "FFFFFF",
+            [
+                "293852", "3A4C6B", "4B5D7D", "576B8F", "657CA3", "7A94C2", "9AB5E6", "AAC3F0",
+                "CCDFFF",
+            ],
+        ),
+        (
+            "1E3251",
+            "FFFFFF",
+            [
+                "293952", "3A4D6B", "4B5F7D", "576D8F", "657EA3", "7A96C2", "9AB7E6", "AAC5F0",
+                "CCE0FF",
+            ],
+        ),
+        (
+            "305B89",
+            "FFFFFF",
+            [
+                "293D52", "3A526B", "4B637D", "57728F", "6583A3", "7A9DC2", "9ABEE6", "AACCF0",
+                "CCE5FF",
+            ],
+        ),
+        (
+            "3ACDF1",
+            "12100F",
+            [
+                "294A52", "3A616B", "4B737D", "57848F", "6597A3", "7AB4C2", "9AD7E6", "AAE2F0",
+                "CCF5FF",
+            ],
+        ),
+    ];
+    /// `CURVE_DEFAULT` as (brightness, saturation) points.
+    const DEFAULT_CURVE: [(u8, u8); 9] = [
+        (32, 50),
+        (42, 46),
+        (49, 40),
+        (56, 39),
+        (64, 38),
+        (76, 37),
+        (90, 33),
+        (94, 29),
+        (100, 20),
+    ];
+
+    fn negate(hex: &str) -> String {
+        to_hex(parse_hex(hex).unwrap().map(|v| 1.0 - v))
+    }
+
+    #[test]
+    fn modulate_matches_magick_text_colors() {
+        for (primary, text, _) in MAGICK_PALETTE {
+            let bri = if is_dark(primary).unwrap() { 188 } else { 16 };
+            assert_eq!(
+                modulate(&negate(primary), bri, 10, 100).unwrap(),
+                text,
+                "text for #{}",
+                primary
+            );
+        }
+    }
+
+    #[test]
+    fn hsb_accents_match_magick() {
+        for (primary, _, accents) in MAGICK_PALETTE {
+            let hue = hsb_hue(primary).unwrap();
+            let derived: Vec<String> = DEFAULT_CURVE
+                .iter()
+                .map(|&(bri, sat)| color_from_hsb(hue, sat, bri))
+                .collect();
+            assert_eq!(derived, accents, "accents for #{}", primary);
+        }
+    }
+
+    #[test]
+    fn normalize_leaves_a_solid_color_unchanged() {
+        // A solid `xc:` image has no range to stretch; the `12100F` text above depends on this.
+        for hex in ["C5320E", "FAF6EF", "000000", "808080"] {
+            let rgb = parse_hex(hex).unwrap();
+            assert_eq!(normalize(&[rgb]), vec![rgb]);
+        }
+        let stretched = normalize(&[[0.25, 0.5, 0.5], [0.75, 0.5, 1.0]]);
+        assert_eq!(stretched, vec![[0.0, 0.5, 0.0], [1.0, 0.5, 1.0]]);
+    }
+
+    #[test]
+    fn modulate_at_100_percent_is_identity() {
+        for hex in ["C5320E", "3ACDF1", "808080"] {
+            assert_eq!(modulate(hex, 100, 100, 100).unwrap(), hex);
+        }
+    }
+
+    #[test]
+    fn hsb_round_trips() {
+        for hex in ["C5320E", "3ACDF1", "050910", "FFFFFF", "000000"] {
+            let (h, s, b) = rgb_to_hsb(parse_hex(hex).unwrap());
+            assert_eq!(to_hex(hsb_to_rgb(h, s, b)), hex);
+        }
+    }
+
+    #[test]
+    fn oklch_round_trips() {
+        for hex in [
+            "C5320E", "3ACDF1", "050910", "7A94C2", "FFFFFF", "000000", "808080",
+        ] {
+            let (l, c, h) = rgb_to_oklch(parse_hex(hex).unwrap());
+            assert_eq!(to_hex(oklch_to_rgb(l, c, h)), hex);
+        }
+    }
+
+    #[test]
+    fn oklch_reduces_chroma_into_srgb_gamut() {
+        for lightness in [0.1, 0.3, 0.5, 0.7, 0.9, 1.0] {
+            for hue in (0..360).step_by(30) {
+                let rgb = oklch_to_rgb(lightness, 0.4, f64::from(hue));
+                assert!(
+                    rgb.iter().all(|v| (-1e-4..=1.0 + 1e-4).contains(v)),
+                    "L={} h={} gave {:?}",
+                    lightness,
+                    hue,
+                    rgb
+                );
+                // Only chroma is reduced: lightness and hue are kept.
+                let (l, c, h) = rgb_to_oklch(rgb.map(|v| v.clamp(0.0, 1.0)));
+                assert!(
+                    (l - lightness).abs() < 1e-3,
+                    "lightness {} became {}",
+                    lightness,
+                    l
+                );
+                if c > 0.02 {
+                    let delta = (h - f64::from(hue) + 540.0).rem_euclid(360.0) - 180.0;
+                    assert!(delta.abs() < 1.0, "hue {} became {}", hue, h);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn oklch_keeps_in_gamut_chroma() {
+        let (l, c, h) = rgb_to_oklch(parse_hex("3ACDF1").unwrap());
+        let (_, kept, _) = rgb_to_oklch(oklch_to_rgb(l, c, h));
+        assert!((kept - c).abs() < 1e-6);
+    }
+
+    #[test]
+    fn contrast_already_met_is_unchanged() {
+        let (text, ratio) = enforce_contrast("FFFFFF", "050910", 4.5).unwrap();
+        assert_eq!(text, "FFFFFF");
+        assert!((ratio - contrast_ratio("FFFFFF", "050910").unwrap()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn contrast_reachable_moves_away_from_background() {
+        for (text, background) in [
+            ("7A94C2", "305B89"),
+            ("305B89", "1E3251"),
+            ("C5320E", "E07040"),
+        ] {
+            let (adjusted, ratio) = enforce_contrast(text, background, 4.5).unwrap();
+            assert!(
+                ratio >= 4.5,
+                "#{} on #{} only reached {:.2}",
+                adjusted,
+                background,
+                ratio
+            );
+            assert!((ratio - contrast_ratio(&adjusted, background).unwrap()).abs() < 1e-9);
+
+            let (old_l, _, old_h) = rgb_to_oklch(parse_hex(text).unwrap());
+            let (new_l, new_c, new_h) = rgb_to_oklch(parse_hex(&adjusted).unwrap());
+            let (bg_l, _, _) = rgb_to_oklch(parse_hex(background).unwrap());
+            assert_eq!(
+                new_l > old_l,
+                old_l >= bg_l,
+                "#{} moved toward #{}",
+                adjusted,
+                background
+            );
+            if new_c > 0.02 {
+                let delta = (new_h - old_h + 540.0).rem_euclid(360.0) - 180.0;
+                assert!(delta.abs() < 2.0, "hue {} became {}", old_h, new_h);
+            }
+        }
+    }
+
+    #[test]
+    fn contrast_falls_back_to_the_other_extreme() {
+        // Lightening a light gray on mid gray cannot reach 7:1, darkening it can.
+        let (adjusted, ratio) = enforce_contrast("C0C0C0", "B0B0B0", 7.0).unwrap();
+        assert!(ratio >= 7.0);
+        assert!(
+            relative_luminance(parse_hex(&adjusted).unwrap())
+                < relative_luminance(parse_hex("B0B0B0").unwrap())
+        );
+    }
+
+    #[test]
+    fn contrast_unreachable_returns_the_best_found() {
+        let (adjusted, ratio) = enforce_contrast("777777", "808080", 10.0).unwrap();
+        assert!(ratio < 10.0);
+        assert_eq!(adjusted, "000000");
+        assert!((ratio - contrast_ratio("000000", "808080").unwrap()).abs() < 1e-9);
+    }
+}
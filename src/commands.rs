@@ -0,0 +1,622 @@
+//! Runs the default extraction pipeline and each CLI subcommand.
+//!
+//! The default invocation extracts (or loads from cache) a palette and writes every output; the subcommands reuse the same steps individually.
+use crate::ansi;
+use crate::atomic::Transaction;
+use crate::backend;
+use crate::cache;
+use crate::cli::{CacheAction, ExtractArgs, WriteArgs};
+use crate::config::{self, AppPaths, Palette, UserConfig};
+use crate::error::WallbashError;
+use crate::hooks;
+use crate::html;
+use crate::output::{self, OutputOptions, PrintFormat, Templates};
+use crate::palette;
+use crate::preview::{self, ColorMode};
+use crate::terminals;
+use crate::wallpaper;
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// A temporary file removed when dropped.
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!(
+                "Failed to remove temporary file {}: {}",
+                self.0.display(),
+                e
+            );
+        }
+    }
+}
+
+/// A saved palette file to render from instead of extracting.
+pub enum PaletteFile<'a> {
+    Json(&'a str),
+    Dcol(&'a str),
+    Base16(&'a str),
+}
+
+impl PaletteFile<'_> {
+    fn load(&self) -> Result<Palette> {
+        let (PaletteFile::Json(file) | PaletteFile::Dcol(file) | PaletteFile::Base16(file)) = self;
+        let path = expand_path(file)?;
+        let palette = match self {
+            PaletteFile::Json(_) => palette::load_palette(&path),
+            PaletteFile::Dcol(_) => palette::load_palette_dcol(&path),
+            PaletteFile::Base16(_) => palette::load_palette_base16(&path),
+        }
+        .context("Failed to load palette")?;
+        info!("Loaded palette from {}", path.display());
+        Ok(palette)
+    }
+}
+
+/// A palette produced by `extract_palette`, with the wallset hash of its image when wallset mode is on.
+struct Extraction {
+    palette: Palette,
+    file_hash: Option<String>,
+}
+
+fn calculate_hash(path: &Path) -> Result<String, WallbashError> {
+    let path_str = path.to_str().ok_or_else(|| {
+        WallbashError::InvalidInput(format!("Invalid path characters: {}", path.display()))
+    })?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(path_str.as_bytes());
+    let result = hasher.finalize();
+
+    Ok(format!("{:x}", result))
+}
+
+
+fn log_palette_preview(palette: &Palette, source: &str) {
+    info!("----- {} Palette Preview -----", source);
+    info!("Mode: {}", palette.mode);
+
+    for i in 0..std::cmp::min(3, palette.primary.len()) {
+        info!("Primary {}: {}", i + 1, palette.primary[i]);
+    }
+
+
+    if let Some(accent) = palette.accents.first().and_then(|accents| accents.first()) {
+        info!("First Accent: {}", accent);
+    }
+    info!("-------------------------------");
+}
+
+/// Resolves the input image from the positional argument or Hyprland detection.
+fn resolve_input_image(args: &ExtractArgs) -> Result<PathBuf> {
+    let input_image_path = match &args.input_image {
+        Some(path) => PathBuf::from(
+            shellexpand::full(path)
+                .map_err(|e| {
+                    WallbashError::PathExpansion(format!(
+                        "Input image path expansion failed: {}",
+                        e
+                    ))
+                })?
+                .into_owned(),
+        ),
+        None if args.detect_hyprland => {
+            wallpaper::detect_hyprland_wallpaper().context("Failed wallpaper detection")?
+        }
+        None => {
+            anyhow::bail!("No input image provided and --detect-hyprland not specified.");
+        }
+    };
+
+    if !input_image_path.is_file() {
+        anyhow::bail!(WallbashError::InvalidInput(format!(
+            "Input image file not found or not a file: {}",
+            input_image_path.display()
+        )));
+    }
+    info!("Using wallpaper: {}", input_image_path.display());
+    Ok(input_image_path)
+}
+
+/// Combines command-line flags and config defaults into the settings that drive extraction.
+fn extraction_settings(
+    args: &ExtractArgs,
+    user_config: &UserConfig,
+    color_backend: &dyn backend::ColorBackend,
+) -> Result<config::ExtractionSettings> {
+    let config_profile = if args.has_profile_flag() {
+        None
+    } else {
+        user_config
+            .color_profile()
+            .context("Invalid color profile in config file")?
+    };
+    let color_profile = match config_profile {
+        Some(profile) => profile,
+        None => {
+            let named = args
+                .profile
+                .as_deref()
+                .map(|name| user_config.resolve_profile(name))
+                .transpose()
+                .context("Invalid --profile selection")?;
+            config::ColorProfile::from_cli(
+                args.vibrant,
+                args.pastel,
+                args.mono,
+                args.custom.clone(),
+                named,
+            )
+            .context("Invalid color profile selection")?
+        }
+    };
+    let config_sort_mode = if args.has_mode_flag() {
+        None
+    } else {
+        user_config
+            .sort_mode()
+            .context("Invalid sort mode in config file")?
+    };
+    let sort_mode = match config_sort_mode {
+        Some(mode) => mode,
+        None => config::SortMode::from_cli(args.dark, args.light)
+            .context("Invalid sort mode selection")?,
+    };
+    Ok(config::ExtractionSettings {
+        color_profile,
+        sort_mode,
+        wallset: args.wallset,
+        backend: color_backend.kind(),
+        colorspace: args.colorspace,
+        min_contrast: args.min_contrast,
+        colors: args.colors,
+        fuzz: args.fuzz,
+        backend_version: color_backend.version(),
+    })
+}
+
+/// Extracts a palette from the input image, or loads it from the cache when nothing relevant changed.
+fn extract_palette(
+    args: &ExtractArgs,
+    user_config: &UserConfig,
+    app_paths: &AppPaths,
+    dry_run: bool,
+) -> Result<Extraction> {
+    let input_image_path = resolve_input_image(args)?;
+    let mut color_backend = backend::create_backend(args.backend, app_paths, dry_run);
+    let settings = extraction_settings(args, user_config, color_backend.as_ref())?;
+
+    let extraction_image_path;
+    let file_hash;
+    // Holds the thumbnail a dry run generates outside the cache, removing it once extraction is done.
+    let mut _dry_run_thumbnail = None;
+
+    if args.wallset {
+        let hash = calculate_hash(&input_image_path)?;
+
+        if !dry_run {
+            app_paths.ensure_thumbs_dir()?;
+        }
+
+        let thumbnail_path = app_paths.thumbs_dir.join(format!("{}.thmb", hash));
+        debug!("Thumbnail path: {}", thumbnail_path.display());
+
+        if dry_run && (!thumbnail_path.exists() || args.force) {
+            let temp_path =
+                std::env::temp_dir().join(format!("wallrust-{}-{}.thmb", std::process::id(), hash));
+            info!(
+                "Dry run: generating the thumbnail in {}",
+                temp_path.display()
+            );
+            let thumbnail = TempFile(temp_path);
+            color_backend
+                .generate_thumbnail(&input_image_path, &thumbnail.0)
+                .context("Failed to generate thumbnail")?;
+            extraction_image_path = thumbnail.0.clone();
+            _dry_run_thumbnail = Some(thumbnail);
+        } else {
+            if !thumbnail_path.exists() || args.force {
+                info!("Generating thumbnail for color extraction...");
+                color_backend
+                    .generate_thumbnail(&input_image_path, &thumbnail_path)
+                    .context("Failed to generate thumbnail")?;
+            } else {
+                debug!("Using existing thumbnail: {}", thumbnail_path.display());
+            }
+            extraction_image_path = thumbnail_path;
+        }
+        file_hash = Some(hash);
+    } else {
+
+        extraction_image_path = input_image_path.clone();
+        file_hash = None;
+    }
+
+    let should_force = if args.force {
+        info!("Force flag set, skipping cache check.");
+        true
+    } else if args.wallset {
+        debug!("Checking if cache needs regeneration for wallset mode...");
+        false
+    } else {
+        false
+    };
+
+    let image_checksum = cache::image_checksum(
+        &app_paths.checksum_index_file,
+        &input_image_path,
+        args.strict_hash,
+        dry_run,
+    )?;
+    let cached_palette = if should_force {
+        None
+    } else {
+        cache::needs_regeneration(
+            &app_paths.palette_cache_dir,
+            &input_image_path,
+            &image_checksum,
+            &settings,
+            dry_run,
+        )?
+    };
+
+    let palette = match cached_palette {
+        Some(mut palette) => {
+            if palette.ansi.is_empty() {
+                palette.ansi = ansi::generate_ansi(&palette)
+                    .context("Failed to generate ANSI palette for cached palette")?;
+            }
+            if args.wallset {
+                info!("Using cached palette (from wallset mode)");
+            } else {
+                info!("Using cached palette (from regular mode)");
+            }
+            log_palette_preview(&palette, "Cached");
+            palette
+        },
+        None => {
+            info!(
+                "Generating new palette (Profile: {}, Mode: {}, Colors: {}, Fuzz: {}, Wallset: {}, Backend: {}, Color space: {})...",
+                settings.color_profile,
+                settings.sort_mode,
+                args.colors,
+                args.fuzz,
+                args.wallset,
+                settings.backend,
+                settings.colorspace
+            );
+
+
+            color_backend
+                .load_image(&extraction_image_path)
+                .context("Failed to load image into color backend")?;
+
+            let mut base_colors_raw = color_backend
+                .extract_kmeans_colors(args.colors, args.fuzz)
+                .context("Failed to extract k-means colors")?;
+
+            if base_colors_raw.len() < args.colors {
+                info!(
+                    "RETRYING K-Means: Found {} colors, need {}. Requesting {}.",
+                    base_colors_raw.len(),
+                    args.colors,
+                    args.colors + 2
+                );
+                base_colors_raw = color_backend
+                    .extract_kmeans_colors(args.colors + 2, args.fuzz)
+                    .context("Failed to extract k-means colors on retry")?;
+
+                if base_colors_raw.len() < args.colors {
+                    anyhow::bail!(WallbashError::NotEnoughColors {
+                        required: args.colors,
+                        found: base_colors_raw.len()
+                    });
+                }
+            }
+
+            let base_hex_colors: Vec<String> = base_colors_raw
+                .into_iter()
+                .map(|(_, hex)| hex)
+                .take(args.colors)
+                .collect();
+
+
+            let generated_palette = palette::generate_palette(
+                &input_image_path,
+                color_backend.as_ref(),
+                base_hex_colors,
+                args.colors,
+                &settings,
+            )
+            .context("Failed to generate full palette")?;
+
+            if dry_run {
+                info!("Dry run: not writing the palette cache.");
+            } else {
+                cache::store_palette(
+                    &app_paths.palette_cache_dir,
+                    &input_image_path,
+                    &image_checksum,
+                    &settings,
+                    &generated_palette,
+                    &user_config.cache,
+                )
+                .context("Failed to write palette cache")?;
+            }
+
+            log_palette_preview(
+                &generated_palette,
+                if args.wallset {
+                    "Thumbnail"
+                } else {
+                    "Original"
+                },
+            );
+            generated_palette
+        }
+    };
+
+    Ok(Extraction { palette, file_hash })
+}
+
+fn write_html(
+    palette: &Palette,
+    app_paths: &AppPaths,
+    transaction: &mut Transaction,
+) -> Result<()> {
+    let html_path = app_paths.output_dir.join("palette.html");
+    html::generate_html(palette, &html_path, transaction)
+        .context("Failed to generate HTML preview")?;
+    if !transaction.is_dry_run() {
+        info!("Generated HTML preview at: {}", html_path.display());
+    }
+    Ok(())
+}
+
+/// What a run writes besides the palette itself.
+struct RunOutputs<'a> {
+    html: bool,
+    /// Hash of the wallpaper in wallset mode, naming the dcol file written to the dcols cache.
+    file_hash: Option<&'a str>,
+    options: &'a OutputOptions,
+}
+
+/// Writes every file of a run into `transaction`, returning the template hooks to run afterwards.
+fn write_run(
+    palette: &Palette,
+    app_paths: &AppPaths,
+    outputs: &RunOutputs,
+    templates: &mut Templates,
+    transaction: &mut Transaction,
+) -> Result<Vec<String>> {
+    let pending_hooks =
+        output::generate_outputs(palette, app_paths, outputs.options, templates, transaction)
+            .context("Failed to generate output files")?;
+    if let Some(file_hash) = outputs.file_hash {
+        if !transaction.is_dry_run() {
+            app_paths.ensure_dcols_dir()?;
+        }
+        let dcol_path = app_paths.dcols_dir.join(format!("{}.dcol", file_hash));
+        output::write_dcol(palette, &dcol_path, transaction)
+            .context("Failed to write dcol file to hashed path")?;
+    }
+    if outputs.html {
+        write_html(palette, app_paths, transaction)?;
+    }
+    if outputs.options.apply_terminals {
+        terminals::save(palette, app_paths, transaction)
+            .context("Failed to save terminal sequences")?;
+    }
+    Ok(pending_hooks)
+}
+
+/// Writes all outputs of a run in a single transaction, then recolors terminals and runs the template and post hooks.
+///
+/// If any file fails to write, every file already written is restored and nothing else runs. With a dry run,
+/// only reports what would change and which hooks would run.
+fn write_outputs(
+    palette: &Palette,
+    app_paths: &AppPaths,
+    user_config: &UserConfig,
+    outputs: &RunOutputs,
+    templates: &mut Templates,
+) -> Result<()> {
+    let dry_run = outputs.options.dry_run;
+    let mut transaction = Transaction::new(dry_run);
+    let pending_hooks = match write_run(palette, app_paths, outputs, templates, &mut transaction) {
+        Ok(pending_hooks) => pending_hooks,
+        Err(e) => {
+            warn!("Output run failed, restoring previous files");
+            transaction.rollback();
+            return Err(e);
+        }
+    };
+
+    if outputs.options.apply_terminals {
+        terminals::recolor(palette, dry_run);
+    }
+    let hook_timeout = outputs.options.hook_timeout;
+    if dry_run {
+        for command in pending_hooks.iter().chain(&user_config.post_hooks) {
+            eprintln!("Would run hook: {}", command);
+        }
+    } else {
+        hooks::run_hooks(&pending_hooks, hook_timeout);
+        hooks::run_hooks(&user_config.post_hooks, hook_timeout);
+    }
+    Ok(())
+}
+
+/// Prints the palette to stdout in the format chosen with `--print`.
+fn print_palette(palette: &Palette, format: Option<PrintFormat>) -> Result<()> {
+    if let Some(format) = format {
+        print!("{}", output::format_palette(palette, format)?);
+    }
+    Ok(())
+}
+
+/// Runs the full pipeline: extract, write all outputs and templates, and optionally the HTML preview.
+pub fn run(
+    args: &ExtractArgs,
+    user_config: &UserConfig,
+    output_dir: Option<String>,
+    html: bool,
+    output_options: &OutputOptions,
+    templates: &mut Templates,
+) -> Result<()> {
+    let app_paths = AppPaths::new(output_dir, output_options.dry_run)
+        .context("Failed to initialize application paths")?;
+    let Extraction { palette, file_hash } =
+        extract_palette(args, user_config, &app_paths, output_options.dry_run)?;
+
+    if output_options.no_write {
+        info!("Not writing any output files (--no-write set)");
+        return print_palette(&palette, output_options.print);
+    }
+
+    let outputs = RunOutputs {
+        html,
+        file_hash: file_hash.as_deref(),
+        options: output_options,
+    };
+    write_outputs(&palette, &app_paths, user_config, &outputs, templates)?;
+
+    if output_options.dry_run {
+        info!("Dry run finished, nothing was written.");
+    } else {
+        info!("Wallbash finished successfully.");
+    }
+    print_palette(&palette, output_options.print)
+}
+
+/// `wallrust extract`: extracts a palette and writes only `wallrust.json`.
+pub fn extract(
+    args: &ExtractArgs,
+    write: &WriteArgs,
+    user_config: &UserConfig,
+    output_dir: Option<String>,
+) -> Result<()> {
+    let app_paths = AppPaths::new(output_dir, write.dry_run)
+        .context("Failed to initialize application paths")?;
+    let Extraction { palette, .. } = extract_palette(args, user_config, &app_paths, write.dry_run)?;
+    if !write.no_write {
+        output::write_json(&palette, &app_paths, &mut Transaction::new(write.dry_run))
+            .context("Failed to write palette JSON")?;
+    }
+    print_palette(&palette, write.print)
+}
+
+/// `wallrust render`, `--from-palette`, and `--from-dcol`: writes all outputs and templates from a saved palette, skipping extraction and the cache.
+pub fn render(
+    palette_file: PaletteFile,
+    user_config: &UserConfig,
+    output_dir: Option<String>,
+    html: bool,
+    output_options: &OutputOptions,
+    templates: &mut Templates,
+) -> Result<()> {
+    let app_paths = AppPaths::new(output_dir, output_options.dry_run)
+        .context("Failed to initialize application paths")?;
+    let palette = palette_file.load()?;
+
+    if output_options.no_write {
+        info!("Not writing any output files (--no-write set)");
+        return print_palette(&palette, output_options.print);
+    }
+    let outputs = RunOutputs {
+        html,
+        file_hash: None,
+        options: output_options,
+    };
+    write_outputs(&palette, &app_paths, user_config, &outputs, templates)?;
+    print_palette(&palette, output_options.print)
+}
+
+/// `wallrust cache`: lists, clears, or prunes the palette cache.
+pub fn cache(action: CacheAction, user_config: &UserConfig) -> Result<()> {
+    let app_paths = AppPaths::new(None, false).context("Failed to initialize application paths")?;
+    match action {
+        CacheAction::List => cache::list_cache(&app_paths),
+        CacheAction::Clear => cache::clear_cache(&app_paths),
+        CacheAction::Prune => cache::prune_cache(&app_paths, &user_config.cache),
+    }
+    .context("Cache operation failed")
+}
+
+/// `wallrust preview`: shows a saved palette, or the cached one, in the terminal or as HTML.
+pub fn preview(
+    palette_file: Option<&str>,
+    output_dir: Option<String>,
+    html: bool,
+    color: ColorMode,
+) -> Result<()> {
+    let app_paths =
+        AppPaths::new(output_dir, false).context("Failed to initialize application paths")?;
+    let palette = match palette_file {
+        Some(file) => {
+            palette::load_palette(&expand_path(file)?).context("Failed to load palette")?
+        }
+        None => match cache::latest_palette(&app_paths.palette_cache_dir)? {
+            Some(palette) => palette,
+            None => anyhow::bail!("No cached palette to preview; pass --palette <FILE>."),
+        },
+    };
+
+    if html {
+        write_html(&palette, &app_paths, &mut Transaction::new(false))
+    } else {
+        preview::print_palette(&palette, color);
+        Ok(())
+    }
+}
+
+/// `wallrust detect`: prints the current Hyprland wallpaper path.
+pub fn detect() -> Result<()> {
+    let path = wallpaper::detect_hyprland_wallpaper().context("Failed wallpaper detection")?;
+    println!("{}", path.display());
+    Ok(())
+}
+
+fn expand_path(path: &str) -> Result<PathBuf> {
+    Ok(PathBuf::from(
+        shellexpand::full(path)
+            .map_err(|e| WallbashError::PathExpansion(format!("Path expansion failed: {}", e)))?
+            .into_owned(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use crate::config::{ColorProfile, SortMode};
+    use crate::native::NativeBackend;
+    use clap::Parser;
+
+    fn settings(args: &[&str]) -> config::ExtractionSettings {
+        let config: UserConfig = toml::from_str("mode = \"dark\"\nprofile = \"pastel\"").unwrap();
+        let cli =
+            Cli::try_parse_from(std::iter::once("wallrust").chain(args.iter().copied())).unwrap();
+        extraction_settings(&cli.extract, &config, &NativeBackend::new()).unwrap()
+    }
+
+    #[test]
+    fn config_mode_and_profile_apply_without_flags() {
+        let settings = settings(&["wall.png"]);
+        assert_eq!(settings.sort_mode, SortMode::Dark);
+        assert_eq!(settings.color_profile, ColorProfile::Pastel);
+    }
+
+    #[test]
+    fn auto_and_profile_flags_override_config() {
+        let settings = settings(&["wall.png", "--auto", "--profile", "default"]);
+        assert_eq!(settings.sort_mode, SortMode::Auto);
+        assert_eq!(settings.color_profile, ColorProfile::Default);
+    }
+}
@@ -1,17 +1,27 @@
 //! Defines configuration types, color profiles, palette structure, and application paths for Wallrust.
 //!
 //! This module contains:
-//! - Color profile and sort mode enums
+//! - Color profile, sort mode, and color space enums
 //! - The main `Palette` struct (used throughout the app and in templates)
+//! - The user config file (`UserConfig`)
 //! - Application path management (`AppPaths`)
 //! - Constants for default values and color curves
+use crate::backend::BackendKind;
 use crate::error::WallbashError;
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::{Path, PathBuf};
 
 pub const DEFAULT_COLORS: usize = 4;
 pub const DEFAULT_FUZZ: u8 = 70;
+pub const DEFAULT_MIN_CONTRAST: f64 = 4.5;
+pub const DEFAULT_CACHE_MAX_ENTRIES: usize = 256;
+pub const DEFAULT_CACHE_MAX_SIZE_MB: u64 = 32;
+pub const DEFAULT_WATCH_INTERVAL_MS: u64 = 1000;
+pub const DEFAULT_WATCH_DEBOUNCE_MS: u64 = 500;
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 10;
 pub const CURVE_DEFAULT: &str = "32 50\n42 46\n49 40\n56 39\n64 38\n76 37\n90 33\n94 29\n100 20";
 pub const CURVE_VIBRANT: &str = "18 99\n32 97\n48 95\n55 90\n70 80\n80 70\n88 60\n94 40\n99 24";
 pub const CURVE_PASTEL: &str = "10 99\n17 66\n24 49\n39 41\n51 37\n58 34\n72 30\n84 26\n99 22";
@@ -26,21 +36,55 @@ pub const PRY_LIGHT_HUE: u8 = 114;
 pub const TXT_DARK_BRI: u8 = 188;
 pub const TXT_LIGHT_BRI: u8 = 16;
 pub const ACCENT_COUNT: usize = 9;
+pub const OKLCH_MAX_CHROMA: f64 = 0.32;
+pub const TXT_OKLCH_DARK_L: f64 = 0.92;
+pub const TXT_OKLCH_LIGHT_L: f64 = 0.2;
+pub const TXT_OKLCH_CHROMA_SCALE: f64 = 0.1;
 
-/// Represents a color profile (default, vibrant, pastel, mono, or custom curve) for palette generation.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Brightness, saturation, and hue percentages used to fill in missing primaries and derive HSB text colors.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Modulation {
+    pub pry_dark_bri: u8,
+    pub pry_dark_sat: u8,
+    pub pry_dark_hue: u8,
+    pub pry_light_bri: u8,
+    pub pry_light_sat: u8,
+    pub pry_light_hue: u8,
+    pub txt_dark_bri: u8,
+    pub txt_light_bri: u8,
+}
+
+impl Default for Modulation {
+    fn default() -> Self {
+        Self {
+            pry_dark_bri: PRY_DARK_BRI,
+            pry_dark_sat: PRY_DARK_SAT,
+            pry_dark_hue: PRY_DARK_HUE,
+            pry_light_bri: PRY_LIGHT_BRI,
+            pry_light_sat: PRY_LIGHT_SAT,
+            pry_light_hue: PRY_LIGHT_HUE,
+            txt_dark_bri: TXT_DARK_BRI,
+            txt_light_bri: TXT_LIGHT_BRI,
+        }
+    }
+}
+
+/// Represents a color profile (default, vibrant, pastel, mono, custom curve, or a named profile from the config file) for palette generation.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub enum ColorProfile {
+    #[default]
     Default,
     Vibrant,
     Pastel,
     Mono,
     Custom(String),
-}
-
-impl Default for ColorProfile {
-    fn default() -> Self {
-        ColorProfile::Default
-    }
+    /// A profile defined under `[profiles.<name>]` in the config file, stored fully resolved so the cache notices edits.
+    Named {
+        name: String,
+        curve: String,
+        modulation: Modulation,
+    },
 }
 
 impl ColorProfile {
@@ -51,6 +95,15 @@ impl ColorProfile {
             ColorProfile::Pastel => CURVE_PASTEL.to_string(),
             ColorProfile::Mono => CURVE_MONO.to_string(),
             ColorProfile::Custom(s) => s.clone(),
+            ColorProfile::Named { curve, .. } => curve.clone(),
+        }
+    }
+
+    /// Returns the modulate constants for this profile; only named profiles can override the defaults.
+    pub fn modulation(&self) -> Modulation {
+        match self {
+            ColorProfile::Named { modulation, .. } => *modulation,
+            _ => Modulation::default(),
         }
     }
     /// Constructs a ColorProfile from CLI flags, ensuring only one profile is selected.
@@ -61,6 +114,7 @@ impl ColorProfile {
         pastel: bool,
         mono: bool,
         custom: Option<String>,
+        named: Option<ColorProfile>,
     ) -> Result<Self, WallbashError> {
         let mut profile = ColorProfile::Default;
         let mut profile_count = 0;
@@ -80,24 +134,39 @@ impl ColorProfile {
         if let Some(custom_curve) = custom {
             let cleaned_curve = custom_curve.replace("\\n", "\n");
             if cleaned_curve.split('\n').count() < ACCENT_COUNT {
-                eprintln!(
-                    "Warning: Custom curve has fewer than {} lines.",
-                    ACCENT_COUNT
-                );
+                warn!("Custom curve has fewer than {} lines.", ACCENT_COUNT);
             }
             profile = ColorProfile::Custom(cleaned_curve);
             profile_count += 1;
         }
+        if let Some(named) = named {
+            profile = named;
+            profile_count += 1;
+        }
 
         if profile_count > 1 {
             Err(WallbashError::InvalidInput(
-                "Only one color profile (--vibrant, --pastel, --mono, --custom) can be specified."
+                "Only one color profile (--vibrant, --pastel, --mono, --custom, --profile) can be specified."
                     .to_string(),
             ))
         } else {
             Ok(profile)
         }
     }
+
+    /// Looks up a built-in profile by name (default, vibrant, pastel, mono).
+    pub fn from_name(name: &str) -> Result<Self, WallbashError> {
+        match name.to_ascii_lowercase().as_str() {
+            "default" => Ok(ColorProfile::Default),
+            "vibrant" => Ok(ColorProfile::Vibrant),
+            "pastel" => Ok(ColorProfile::Pastel),
+            "mono" => Ok(ColorProfile::Mono),
+            _ => Err(WallbashError::InvalidInput(format!(
+                "Unknown color profile '{}'",
+                name
+            ))),
+        }
+    }
 }
 
 impl std::fmt::Display for ColorProfile {
@@ -108,6 +177,7 @@ impl std::fmt::Display for ColorProfile {
             ColorProfile::Pastel => write!(f, "pastel"),
             ColorProfile::Mono => write!(f, "mono"),
             ColorProfile::Custom(_) => write!(f, "custom"),
+            ColorProfile::Named { name, .. } => write!(f, "{}", name),
         }
     }
 }
@@ -137,6 +207,19 @@ impl SortMode {
             Ok(SortMode::Auto)
         }
     }
+
+    /// Parses a sort mode name (auto, dark, light).
+    pub fn from_name(name: &str) -> Result<Self, WallbashError> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Ok(SortMode::Auto),
+            "dark" => Ok(SortMode::Dark),
+            "light" => Ok(SortMode::Light),
+            _ => Err(WallbashError::InvalidInput(format!(
+                "Unknown sort mode '{}'",
+                name
+            ))),
+        }
+    }
 }
 
 impl std::fmt::Display for SortMode {
@@ -149,37 +232,372 @@ impl std::fmt::Display for SortMode {
     }
 }
 
+/// Selects the color space used for sorting primaries and deriving text and accent colors.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+    /// ImageMagick-compatible HSB accents and sRGB luminance sorting.
+    #[default]
+    Hsb,
+    /// Perceptual OKLCH, so accents at the same curve step share the same lightness across hues.
+    Oklch,
+}
+
+impl std::fmt::Display for ColorSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorSpace::Hsb => write!(f, "hsb"),
+            ColorSpace::Oklch => write!(f, "oklch"),
+        }
+    }
+}
+
 /// The main palette structure containing all extracted and generated color data.
 ///
 /// This struct is used throughout Wallrust and is available as context in Tera templates.
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Palette {
     pub mode: String,
+    #[serde(default)]
     pub wallpaper: String,
     pub primary: Vec<String>,
     pub text: Vec<String>,
     pub accents: Vec<Vec<String>>,
+    #[serde(default)]
     pub primary_rgba: Vec<String>,
+    #[serde(default)]
     pub text_rgba: Vec<String>,
+    #[serde(default)]
     pub accents_rgba: Vec<Vec<String>>,
     #[serde(default = "default_is_dark")]
     pub is_dark: bool,
+    #[serde(default)]
+    pub ansi: AnsiPalette,
+    #[serde(default)]
+    pub text_contrast: Vec<f64>,
+}
+
+/// A 16-color ANSI terminal palette plus the special terminal roles, derived from the extracted colors.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AnsiPalette {
+    pub color0: String,
+    pub color1: String,
+    pub color2: String,
+    pub color3: String,
+    pub color4: String,
+    pub color5: String,
+    pub color6: String,
+    pub color7: String,
+    pub color8: String,
+    pub color9: String,
+    pub color10: String,
+    pub color11: String,
+    pub color12: String,
+    pub color13: String,
+    pub color14: String,
+    pub color15: String,
+    pub foreground: String,
+    pub background: String,
+    pub cursor: String,
+    pub selection: String,
+}
+
+impl AnsiPalette {
+    /// Returns color0 through color15 in order.
+    pub fn colors(&self) -> [&str; 16] {
+        [
+            &self.color0,
+            &self.color1,
+            &self.color2,
+            &self.color3,
+            &self.color4,
+            &self.color5,
+            &self.color6,
+            &self.color7,
+            &self.color8,
+            &self.color9,
+            &self.color10,
+            &self.color11,
+            &self.color12,
+            &self.color13,
+            &self.color14,
+            &self.color15,
+        ]
+    }
+
+    /// Returns color0 through color15 followed by the foreground, background, cursor, and selection, for editing in place.
+    pub fn values_mut(&mut self) -> [&mut String; 20] {
+        [
+            &mut self.color0,
+            &mut self.color1,
+            &mut self.color2,
+            &mut self.color3,
+            &mut self.color4,
+            &mut self.color5,
+            &mut self.color6,
+            &mut self.color7,
+            &mut self.color8,
+            &mut self.color9,
+            &mut self.color10,
+            &mut self.color11,
+            &mut self.color12,
+            &mut self.color13,
+            &mut self.color14,
+            &mut self.color15,
+            &mut self.foreground,
+            &mut self.background,
+            &mut self.cursor,
+            &mut self.selection,
+        ]
+    }
+
+    /// Returns true if the palette has not been generated, e.g. when loaded from an older cache.
+    pub fn is_empty(&self) -> bool {
+        self.background.is_empty()
+    }
 }
 
 fn default_is_dark() -> bool {
     false
 }
 
+/// Settings that affect the generated palette, used both to drive extraction and to validate the cache.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtractionSettings {
+    pub color_profile: ColorProfile,
+    pub sort_mode: SortMode,
+    #[serde(default)]
+    pub wallset: bool,
+    #[serde(default)]
+    pub backend: BackendKind,
+    #[serde(default)]
+    pub colorspace: ColorSpace,
+    #[serde(default)]
+    pub min_contrast: f64,
+    #[serde(default)]
+    pub colors: usize,
+    #[serde(default)]
+    pub fuzz: u8,
+    /// Version of the backend (or of ImageMagick), so upgrades do not reuse palettes from older algorithms.
+    #[serde(default)]
+    pub backend_version: String,
+}
+
+/// Size, modification time, and inode of an image file. While these match, the file's cached checksum is trusted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub size: u64,
+    pub mtime_secs: u64,
+    pub mtime_nanos: u32,
+    pub inode: u64,
+}
+
+impl FileStamp {
+    /// Reads the stamp of the file at `path`.
+    pub fn of(path: &Path) -> Result<Self, WallbashError> {
+        let metadata = fs::metadata(path)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(&metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
+        Ok(Self {
+            size: metadata.len(),
+            mtime_secs: mtime.as_secs(),
+            mtime_nanos: mtime.subsec_nanos(),
+            inode,
+        })
+    }
+}
+
 /// Stores all data needed for palette caching and cache validation.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CacheData {
     pub image_path: String,
     pub image_checksum: String,
-    pub color_profile: ColorProfile,
-    pub sort_mode: SortMode,
-    pub palette: Palette,
     #[serde(default)]
-    pub wallset: bool,
+    pub image_stamp: Option<FileStamp>,
+    #[serde(flatten)]
+    pub settings: ExtractionSettings,
+    pub palette: Palette,
+}
+
+/// User defaults read from `~/.config/wallrust/config.toml`. Command-line flags take precedence over every value here.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UserConfig {
+    pub colors: Option<usize>,
+    pub fuzz: Option<u8>,
+    /// Profile name: one of `profiles`, or built-in default, vibrant, pastel, or mono.
+    pub profile: Option<String>,
+    /// Custom color curve, one `<brightness> <saturation>` pair per line. Takes precedence over `profile`.
+    pub curve: Option<String>,
+    /// Sort mode: auto, dark, or light.
+    pub mode: Option<String>,
+    pub output_dir: Option<String>,
+    pub html: Option<bool>,
+    pub wallset: Option<bool>,
+    pub no_templates: Option<bool>,
+    pub backend: Option<BackendKind>,
+    pub colorspace: Option<ColorSpace>,
+    pub min_contrast: Option<f64>,
+    /// Named profiles, selectable with `--profile <name>` or `profile = "<name>"`.
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    pub cache: CacheConfig,
+    /// Shell commands run after all outputs have been written.
+    pub post_hooks: Vec<String>,
+    /// Maximum run time, in seconds, of each template and post hook.
+    pub hook_timeout: Option<u64>,
+}
+
+/// Palette cache limits under `[cache]`. The least recently used entries are evicted first.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub max_entries: Option<usize>,
+    pub max_size_mb: Option<u64>,
+    /// Always hash images instead of trusting an unchanged size, mtime, and inode.
+    pub strict_hash: Option<bool>,
+}
+
+impl CacheConfig {
+    pub fn max_entries(&self) -> usize {
+        self.max_entries.unwrap_or(DEFAULT_CACHE_MAX_ENTRIES)
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_size_mb.unwrap_or(DEFAULT_CACHE_MAX_SIZE_MB) * 1024 * 1024
+    }
+}
+
+/// A user-defined profile under `[profiles.<name>]`: a curve of `[brightness, saturation]` points plus optional modulate overrides.
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "ProfileTable")]
+pub struct ProfileConfig {
+    pub curve: Vec<[u8; 2]>,
+    pub modulation: Modulation,
+}
+
+/// A `[profiles.<name>]` table as written, collecting the keys neither the curve nor `Modulation` knows.
+#[derive(Deserialize)]
+struct ProfileTable {
+    curve: Vec<[u8; 2]>,
+    #[serde(flatten)]
+    modulation: Modulation,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+impl TryFrom<ProfileTable> for ProfileConfig {
+    type Error = String;
+
+    fn try_from(table: ProfileTable) -> Result<Self, Self::Error> {
+        if !table.unknown.is_empty() {
+            let keys: Vec<_> = table.unknown.into_keys().collect();
+            return Err(format!("unknown profile field(s) {}", keys.join(", ")));
+        }
+        Ok(Self {
+            curve: table.curve,
+            modulation: table.modulation,
+        })
+    }
+}
+
+impl UserConfig {
+    /// Loads the user config from `path_override`, or from the default location if None.
+    ///
+    /// A missing default config file yields empty defaults; a missing override is an error.
+    pub fn load(path_override: Option<&str>) -> Result<Self, WallbashError> {
+        let path = match path_override {
+            Some(path) => {
+                let path = PathBuf::from(
+                    shellexpand::full(path)
+                        .map_err(|e| {
+                            WallbashError::PathExpansion(format!(
+                                "Config path expansion failed: {}",
+                                e
+                            ))
+                        })?
+                        .into_owned(),
+                );
+                if !path.is_file() {
+                    return Err(WallbashError::Config(format!(
+                        "Config file not found: {}",
+                        path.display()
+                    )));
+                }
+                path
+            }
+            None => {
+                let path = wallrust_config_dir()?.join("config.toml");
+                if !path.is_file() {
+                    return Ok(Self::default());
+                }
+                path
+            }
+        };
+
+        debug!("Loading config file: {}", path.display());
+        let content = fs::read_to_string(&path)?;
+        toml::from_str(&content)
+            .map_err(|e| WallbashError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Returns the color profile configured via `curve` or `profile`, if any.
+    pub fn color_profile(&self) -> Result<Option<ColorProfile>, WallbashError> {
+        if let Some(curve) = &self.curve {
+            return Ok(Some(ColorProfile::Custom(curve.replace("\\n", "\n"))));
+        }
+        self.profile
+            .as_deref()
+            .map(|name| self.resolve_profile(name))
+            .transpose()
+    }
+
+    /// Resolves a profile name, preferring profiles defined in the config over the built-in ones.
+    pub fn resolve_profile(&self, name: &str) -> Result<ColorProfile, WallbashError> {
+        let Some(profile) = self.profiles.get(name) else {
+            return ColorProfile::from_name(name);
+        };
+        if profile.curve.len() < ACCENT_COUNT {
+            warn!(
+                "Profile '{}' has fewer than {} curve points.",
+                name, ACCENT_COUNT
+            );
+        }
+        let curve = profile
+            .curve
+            .iter()
+            .map(|[bri, sat]| format!("{} {}", bri, sat))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(ColorProfile::Named {
+            name: name.to_string(),
+            curve,
+            modulation: profile.modulation,
+        })
+    }
+
+    /// Returns the maximum run time of each hook.
+    pub fn hook_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.hook_timeout.unwrap_or(DEFAULT_HOOK_TIMEOUT_SECS))
+    }
+
+    /// Returns the configured sort mode, if any.
+    pub fn sort_mode(&self) -> Result<Option<SortMode>, WallbashError> {
+        self.mode.as_deref().map(SortMode::from_name).transpose()
+    }
+}
+
+/// Returns `~/.config/wallrust` (or the platform equivalent).
+fn wallrust_config_dir() -> Result<PathBuf, WallbashError> {
+    let home_dir = dirs::home_dir().ok_or(WallbashError::HomeDirNotFound)?;
+    Ok(dirs::config_dir()
+        .map(|p| p.join("wallrust"))
+        .unwrap_or_else(|| home_dir.join(".config/wallrust")))
 }
 
 /// Stores all relevant file and directory paths used by Wallrust for output, templates, and cache.
@@ -187,21 +605,23 @@ pub struct CacheData {
 pub struct AppPaths {
     pub template_dir: PathBuf,
     pub output_dir: PathBuf,
+    pub cache_dir: PathBuf,
     pub thumbs_dir: PathBuf,
     pub dcols_dir: PathBuf,
     pub mpc_cache_file: PathBuf,
-    pub wallbash_cache_file: PathBuf,
+    pub palette_cache_dir: PathBuf,
+    pub checksum_index_file: PathBuf,
+    pub sequences_file: PathBuf,
+    pub pywal_cache_dir: PathBuf,
 }
 
 impl AppPaths {
-    /// Constructs all relevant file and directory paths for Wallrust, creating them if necessary.
+    /// Constructs all relevant file and directory paths for Wallrust, creating them if necessary unless `dry_run` is set.
     ///
     /// If `output_dir_override` is None, uses the current working directory for output.
-    pub fn new(output_dir_override: Option<String>) -> Result<Self, WallbashError> {
+    pub fn new(output_dir_override: Option<String>, dry_run: bool) -> Result<Self, WallbashError> {
         let home_dir = dirs::home_dir().ok_or(WallbashError::HomeDirNotFound)?;
-        let config_dir = dirs::config_dir()
-            .map(|p| p.join("wallrust"))
-            .unwrap_or_else(|| home_dir.join(".config/wallrust"));
+        let config_dir = wallrust_config_dir()?;
         let cache_dir = dirs::cache_dir()
             .map(|p| p.join("wallrust"))
             .unwrap_or_else(|| home_dir.join(".cache/wallrust"));
@@ -220,7 +640,7 @@ impl AppPaths {
             ),
             None => {
                 let wd = std::env::current_dir()?;
-                println!(
+                info!(
                     "No output directory specified. Using current directory: {}",
                     wd.display()
                 );
@@ -228,44 +648,115 @@ impl AppPaths {
             }
         };
 
-        fs::create_dir_all(&config_dir)?;
-        fs::create_dir_all(&cache_dir)?;
-        fs::create_dir_all(&template_dir)?;
+        if !dry_run {
+            fs::create_dir_all(&config_dir)?;
+            fs::create_dir_all(&cache_dir)?;
+            fs::create_dir_all(&template_dir)?;
+        }
 
         let mpc_cache_file = cache_dir.join("wallbash.mpc");
-        let wallbash_cache_file = cache_dir.join("wallbash_cache.json");
+        let palette_cache_dir = cache_dir.join("palettes");
+        let checksum_index_file = cache_dir.join("checksums.json");
+        let sequences_file = cache_dir.join("sequences");
+        let pywal_cache_dir = dirs::cache_dir()
+            .unwrap_or_else(|| home_dir.join(".cache"))
+            .join("wal");
 
         Ok(Self {
             template_dir,
             output_dir,
+            cache_dir,
             thumbs_dir,
             dcols_dir,
             mpc_cache_file,
-            wallbash_cache_file,
+            palette_cache_dir,
+            checksum_index_file,
+            sequences_file,
+            pywal_cache_dir,
         })
     }
     
     /// Ensures the thumbnail directory exists, creating it if needed.
     pub fn ensure_thumbs_dir(&self) -> Result<(), WallbashError> {
-        println!("Ensuring thumbnail directory exists: {}", self.thumbs_dir.display());
+        debug!(
+            "Ensuring thumbnail directory exists: {}",
+            self.thumbs_dir.display()
+        );
         if !self.thumbs_dir.exists() {
-            println!("Creating thumbnail directory");
+            debug!("Creating thumbnail directory");
             fs::create_dir_all(&self.thumbs_dir)?;
         } else {
-            println!("Thumbnail directory already exists");
+            debug!("Thumbnail directory already exists");
         }
         Ok(())
     }
     
     /// Ensures the dcols directory exists, creating it if needed.
     pub fn ensure_dcols_dir(&self) -> Result<(), WallbashError> {
-        println!("Ensuring dcols directory exists: {}", self.dcols_dir.display());
+        debug!(
+            "Ensuring dcols directory exists: {}",
+            self.dcols_dir.display()
+        );
         if !self.dcols_dir.exists() {
-            println!("Creating dcols directory");
+            debug!("Creating dcols directory");
             fs::create_dir_all(&self.dcols_dir)?;
         } else {
-            println!("Dcols directory already exists");
+            debug!("Dcols directory already exists");
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILES: &str = r#"
+        profile = "dusk"
+
+        [profiles.dusk]
+        curve = [[20, 60], [30, 55], [40, 50], [50, 45], [60, 40], [70, 35], [80, 30], [90, 25], [95, 20]]
+        pry_dark_bri = 90
+        txt_light_bri = 12
+    "#;
+
+    #[test]
+    fn named_profiles_reach_extraction_settings() {
+        let config: UserConfig = toml::from_str(PROFILES).unwrap();
+        let profile = config.color_profile().unwrap().unwrap();
+        assert_eq!(profile.to_string(), "dusk");
+        assert_eq!(
+            profile.to_curve_string(),
+            "20 60\n30 55\n40 50\n50 45\n60 40\n70 35\n80 30\n90 25\n95 20"
+        );
+
+        let modulation = profile.modulation();
+        assert_eq!(modulation.pry_dark_bri, 90);
+        assert_eq!(modulation.txt_light_bri, 12);
+        assert_eq!(
+            Modulation {
+                pry_dark_bri: PRY_DARK_BRI,
+                txt_light_bri: TXT_LIGHT_BRI,
+                ..modulation
+            },
+            Modulation::default(),
+            "keys not given keep their defaults"
+        );
+
+        // `--profile dusk` resolves to the same profile; built-in names still work.
+        assert_eq!(config.resolve_profile("dusk").unwrap(), profile);
+        assert_eq!(
+            config.resolve_profile("pastel").unwrap(),
+            ColorProfile::Pastel
+        );
+    }
+
+    #[test]
+    fn profiles_reject_unknown_keys() {
+        let config = PROFILES.replace("pry_dark_bri", "pry_drak_bri");
+        let error = toml::from_str::<UserConfig>(&config)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("pry_drak_bri"), "{}", error);
+    }
+}
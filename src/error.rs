@@ -1,6 +1,6 @@
 //! Defines Wallrust's custom error types for robust error handling across all modules.
 //!
-//! This module provides the `WallbashError` enum, which covers I/O, ImageMagick, template, and configuration errors, and integrates with anyhow and thiserror for ergonomic error management.
+//! This module provides the `WallbashError` enum, which covers I/O, ImageMagick, image decoding, template, and configuration errors, and integrates with anyhow and thiserror for ergonomic error management.
 
 /// Central error type for Wallrust, covering all major error cases.
 ///
@@ -43,6 +43,9 @@ pub enum WallbashError {
     #[error("JSON serialization/deserialization error: {0}")]
     JsonError(#[from] serde_json::Error),
 
+    #[error("YAML error: {0}")]
+    YamlError(#[from] serde_yaml::Error),
+
     #[error("Template error: {0}")]
     TemplateError(#[from] tera::Error),
 
@@ -51,4 +54,10 @@ pub enum WallbashError {
 
     #[error("Could not determine home directory")]
     HomeDirNotFound,
+
+    #[error("Config file error: {0}")]
+    Config(String),
+
+    #[error("Image decoding error: {0}")]
+    ImageDecode(#[from] image::ImageError),
 }
@@ -0,0 +1,447 @@
+//! Tera filters and functions for deriving colors inside templates.
+//!
+//! Every filter takes a hex color with or without a leading `#` and, when it returns a color, keeps the input's prefix style, so `{{ primary[0] | lighten(amount=10) }}` yields `AABBCC` and `{{ "#aabbcc" | lighten }}` yields `#AABBCC`. Lightness and saturation changes are absolute HSL percentage points, as in Sass. Each filter is also registered as a function of the same name that takes the color as its `color` argument, e.g. `{{ mix(color=primary[0], with=text[0]) }}`.
+use crate::color;
+use std::collections::HashMap;
+use tera::{Tera, Value};
+
+const DEFAULT_AMOUNT: f64 = 10.0;
+const DEFAULT_MIX_WEIGHT: f64 = 50.0;
+
+/// A color filter, which is also exposed as a function.
+type ColorFilter = fn(&Value, &HashMap<String, Value>) -> tera::Result<Value>;
+
+const FILTERS: [(&str, ColorFilter); 12] = [
+    ("lighten", lighten),
+    ("darken", darken),
+    ("saturate", saturate),
+    ("desaturate", desaturate),
+    ("mix", mix),
+    ("alpha", alpha),
+    ("rgba", rgba),
+    ("to_rgb", to_rgb),
+    ("to_hsl", to_hsl),
+    ("invert", invert),
+    ("contrast_with", contrast_with),
+    ("best_text_on", best_text_on),
+];
+
+/// Registers all color filters on `tera`, plus a function of the same name for each.
+pub fn register(tera: &mut Tera) {
+    for (name, filter) in FILTERS {
+        tera.register_filter(name, filter);
+        tera.register_function(name, as_function(name, filter));
+    }
+}
+
+/// Wraps a filter as a function that reads the input color from its `color` argument.
+///
+/// `contrast_with` already uses `color` for the other color, so its function form takes that one as `with`, like `mix`.
+fn as_function(
+    name: &'static str,
+    filter: ColorFilter,
+) -> impl Fn(&HashMap<String, Value>) -> tera::Result<Value> + Send + Sync {
+    move |args| {
+        let mut args = args.clone();
+        let color = args.remove("color").ok_or_else(|| {
+            tera::Error::msg(format!("Function `{}` requires a `color` argument", name))
+        })?;
+        if name == "contrast_with"
+            && let Some(other) = args.remove("with")
+        {
+            args.insert("color".to_string(), other);
+        }
+        filter(&color, &args)
+    }
+}
+
+/// A filter input color and whether it was written with a leading `#`.
+struct Input {
+    rgb: [f64; 3],
+    hash: bool,
+}
+
+impl Input {
+    fn output(&self, rgb: [f64; 3]) -> Value {
+        let hex = color::to_hex(rgb);
+        Value::String(if self.hash { format!("#{}", hex) } else { hex })
+    }
+}
+
+fn parse_color(value: &Value, filter: &str) -> tera::Result<[f64; 3]> {
+    let hex = value.as_str().ok_or_else(|| {
+        tera::Error::msg(format!("Filter `{}` expects a hex color string", filter))
+    })?;
+    color::parse_hex(hex.trim())
+        .map_err(|e| tera::Error::msg(format!("Filter `{}`: {}", filter, e)))
+}
+
+fn input(value: &Value, filter: &str) -> tera::Result<Input> {
+    Ok(Input {
+        rgb: parse_color(value, filter)?,
+        hash: value.as_str().is_some_and(|s| s.trim().starts_with('#')),
+    })
+}
+
+fn number_arg(
+    args: &HashMap<String, Value>,
+    name: &str,
+    default: f64,
+    filter: &str,
+) -> tera::Result<f64> {
+    match args.get(name) {
+        None => Ok(default),
+        Some(value) => value.as_f64().ok_or_else(|| {
+            tera::Error::msg(format!(
+                "Filter `{}`: argument `{}` must be a number",
+                filter, name
+            ))
+        }),
+    }
+}
+
+fn color_arg(args: &HashMap<String, Value>, name: &str, filter: &str) -> tera::Result<[f64; 3]> {
+    let value = args.get(name).ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Filter `{}` requires a `{}` color argument",
+            filter, name
+        ))
+    })?;
+    parse_color(value, filter)
+}
+
+/// Shifts HSL saturation and lightness by the given fractions.
+fn adjust_hsl(rgb: [f64; 3], saturation_delta: f64, lightness_delta: f64) -> [f64; 3] {
+    let (h, s, l) = color::rgb_to_hsl(rgb);
+    color::hsl_to_rgb(
+        h,
+        (s + saturation_delta).clamp(0.0, 1.0),
+        (l + lightness_delta).clamp(0.0, 1.0),
+    )
+}
+
+/// `lighten(amount=10)`: raises HSL lightness by `amount` percentage points.
+fn lighten(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let input = input(value, "lighten")?;
+    let amount = number_arg(args, "amount", DEFAULT_AMOUNT, "lighten")?;
+    Ok(input.output(adjust_hsl(input.rgb, 0.0, amount / 100.0)))
+}
+
+/// `darken(amount=10)`: lowers HSL lightness by `amount` percentage points.
+fn darken(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let input = input(value, "darken")?;
+    let amount = number_arg(args, "amount", DEFAULT_AMOUNT, "darken")?;
+    Ok(input.output(adjust_hsl(input.rgb, 0.0, -amount / 100.0)))
+}
+
+/// `saturate(amount=10)`: raises HSL saturation by `amount` percentage points.
+fn saturate(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let input = input(value, "saturate")?;
+    let amount = number_arg(args, "amount", DEFAULT_AMOUNT, "saturate")?;
+    Ok(input.output(adjust_hsl(input.rgb, amount / 100.0, 0.0)))
+}
+
+/// `desaturate(amount=10)`: lowers HSL saturation by `amount` percentage points.
+fn desaturate(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let input = input(value, "desaturate")?;
+    let amount = number_arg(args, "amount", DEFAULT_AMOUNT, "desaturate")?;
+    Ok(input.output(adjust_hsl(input.rgb, -amount / 100.0, 0.0)))
+}
+
+/// `mix(with="FFFFFF", weight=50)`: blends in `weight` percent of the `with` color.
+fn mix(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let input = input(value, "mix")?;
+    let other = color_arg(args, "with", "mix")?;
+    let weight = (number_arg(args, "weight", DEFAULT_MIX_WEIGHT, "mix")? / 100.0).clamp(0.0, 1.0);
+    let mut rgb = input.rgb;
+    for (channel, other) in rgb.iter_mut().zip(other) {
+        *channel += (other - *channel) * weight;
+    }
+    Ok(input.output(rgb))
+}
+
+/// `alpha(value=0.5)`: appends an alpha channel, giving 8-digit hex such as `AABBCC80`.
+fn alpha(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let input = input(value, "alpha")?;
+    let alpha = number_arg(args, "value", 1.0, "alpha")?.clamp(0.0, 1.0);
+    let Value::String(hex) = input.output(input.rgb) else {
+        unreachable!("Input::output always returns a string");
+    };
+    Ok(Value::String(format!(
+        "{}{:02X}",
+        hex,
+        (alpha * 255.0).round() as u8
+    )))
+}
+
+/// `rgba(alpha=1.0)`: formats the color as `rgba(r,g,b,a)`.
+fn rgba(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let [r, g, b] = parse_color(value, "rgba")?.map(|v| (v * 255.0).round() as u8);
+    let alpha = number_arg(args, "alpha", 1.0, "rgba")?.clamp(0.0, 1.0);
+    Ok(Value::String(format!("rgba({},{},{},{})", r, g, b, alpha)))
+}
+
+/// `to_rgb`: the 0-255 channels as `r,g,b`.
+fn to_rgb(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let [r, g, b] = parse_color(value, "to_rgb")?.map(|v| (v * 255.0).round() as u8);
+    Ok(Value::String(format!("{},{},{}", r, g, b)))
+}
+
+/// `to_hsl`: hue in degrees and saturation/lightness in percent, as `h,s%,l%`.
+fn to_hsl(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let (h, s, l) = color::rgb_to_hsl(parse_color(value, "to_hsl")?);
+    Ok(Value::String(format!(
+        "{},{}%,{}%",
+        (h * 360.0).round(),
+        (s * 100.0).round(),
+        (l * 100.0).round()
+    )))
+}
+
+/// `invert`: the RGB negative.
+fn invert(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let input = input(value, "invert")?;
+    Ok(input.output(input.rgb.map(|v| 1.0 - v)))
+}
+
+/// `contrast_with(color="000000")`: WCAG contrast ratio against `color`, rounded to 2 decimals.
+fn contrast_with(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let rgb = parse_color(value, "contrast_with")?;
+    let other = color_arg(args, "color", "contrast_with")?;
+    let ratio = color::rgb_contrast_ratio(rgb, other);
+    Ok(Value::from((ratio * 100.0).round() / 100.0))
+}
+
+/// `best_text_on(candidates=["FFFFFF", "000000"])`: the candidate with the highest contrast on this background.
+fn best_text_on(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let background = parse_color(value, "best_text_on")?;
+    let default_candidates = vec![Value::from("FFFFFF"), Value::from("000000")];
+    let candidates = match args.get("candidates") {
+        Some(Value::Array(candidates)) if !candidates.is_empty() => candidates,
+        Some(_) => {
+            return Err(tera::Error::msg(
+                "Filter `best_text_on`: `candidates` must be a non-empty array of colors",
+            ));
+        }
+        None => &default_candidates,
+    };
+
+    let mut best: Option<(&Value, f64)> = None;
+    for candidate in candidates {
+        let ratio = color::rgb_contrast_ratio(parse_color(candidate, "best_text_on")?, background);
+        if best.is_none_or(|(_, best_ratio)| ratio > best_ratio) {
+            best = Some((candidate, ratio));
+        }
+    }
+    Ok(best
+        .map(|(candidate, _)| candidate.clone())
+        .unwrap_or(Value::Null))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(pairs: &[(&str, Value)]) -> HashMap<String, Value> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect()
+    }
+
+    fn apply(
+        filter: fn(&Value, &HashMap<String, Value>) -> tera::Result<Value>,
+        input: &str,
+        pairs: &[(&str, Value)],
+    ) -> Value {
+        filter(&Value::from(input), &args(pairs)).unwrap()
+    }
+
+    #[test]
+    fn lighten_and_darken_keep_the_prefix_style() {
+        assert_eq!(apply(lighten, "808080", &[]), "9A9A9A");
+        assert_eq!(apply(darken, "#808080", &[]), "#676767");
+        assert_eq!(
+            apply(lighten, " #808080 ", &[("amount", Value::from(0))]),
+            "#808080"
+        );
+    }
+
+    #[test]
+    fn lighten_and_darken_clamp_at_white_and_black() {
+        assert_eq!(
+            apply(lighten, "F0F0F0", &[("amount", Value::from(50))]),
+            "FFFFFF"
+        );
+        assert_eq!(
+            apply(darken, "101010", &[("amount", Value::from(50))]),
+            "000000"
+        );
+        assert_eq!(apply(lighten, "FFFFFF", &[]), "FFFFFF");
+        assert_eq!(apply(darken, "000000", &[]), "000000");
+        // A negative amount works in the other direction.
+        assert_eq!(
+            apply(lighten, "808080", &[("amount", Value::from(-10))]),
+            "676767"
+        );
+    }
+
+    #[test]
+    fn lighten_rejects_bad_input() {
+        assert!(lighten(&Value::from("zzzzzz"), &args(&[])).is_err());
+        assert!(lighten(&Value::from(42), &args(&[])).is_err());
+        assert!(
+            lighten(
+                &Value::from("808080"),
+                &args(&[("amount", Value::from("lots"))])
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn alpha_appends_the_alpha_byte() {
+        assert_eq!(
+            apply(alpha, "AABBCC", &[("value", Value::from(0.5))]),
+            "AABBCC80"
+        );
+        assert_eq!(apply(alpha, "#aabbcc", &[]), "#AABBCCFF");
+        assert_eq!(
+            apply(alpha, "AABBCC", &[("value", Value::from(0))]),
+            "AABBCC00"
+        );
+        assert_eq!(
+            apply(alpha, "AABBCC", &[("value", Value::from(2))]),
+            "AABBCCFF"
+        );
+    }
+
+    #[test]
+    fn rgba_formats_channels_and_clamped_alpha() {
+        assert_eq!(apply(rgba, "#FF8000", &[]), "rgba(255,128,0,1)");
+        assert_eq!(
+            apply(rgba, "102030", &[("alpha", Value::from(0.25))]),
+            "rgba(16,32,48,0.25)"
+        );
+        assert_eq!(
+            apply(rgba, "102030", &[("alpha", Value::from(-1))]),
+            "rgba(16,32,48,0)"
+        );
+    }
+
+    #[test]
+    fn best_text_on_picks_the_highest_contrast() {
+        assert_eq!(apply(best_text_on, "101010", &[]), "FFFFFF");
+        assert_eq!(apply(best_text_on, "F0F0F0", &[]), "000000");
+        let candidates = Value::from(vec!["#FFFF00", "#0000FF"]);
+        assert_eq!(
+            apply(
+                best_text_on,
+                "202020",
+                &[("candidates", candidates.clone())]
+            ),
+            "#FFFF00"
+        );
+        assert_eq!(
+            apply(best_text_on, "E0E0E0", &[("candidates", candidates)]),
+            "#0000FF"
+        );
+    }
+
+    #[test]
+    fn best_text_on_rejects_bad_candidates() {
+        let empty = args(&[("candidates", Value::Array(Vec::new()))]);
+        assert!(best_text_on(&Value::from("101010"), &empty).is_err());
+        let not_colors = args(&[("candidates", Value::from(vec!["white"]))]);
+        assert!(best_text_on(&Value::from("101010"), &not_colors).is_err());
+    }
+
+    // The expected values below are the ones documented in the README and docs/Templating.md.
+
+    #[test]
+    fn lighten_matches_the_docs() {
+        assert_eq!(
+            apply(lighten, "AABBCC", &[("amount", Value::from(20))]),
+            "EAEEF2"
+        );
+    }
+
+    #[test]
+    fn saturate_and_desaturate_shift_saturation() {
+        assert_eq!(apply(saturate, "AABBCC", &[]), "A3BBD3");
+        assert_eq!(apply(desaturate, "AABBCC", &[]), "B1BBC5");
+        assert_eq!(
+            apply(desaturate, "#AABBCC", &[("amount", Value::from(100))]),
+            "#BBBBBB"
+        );
+    }
+
+    #[test]
+    fn mix_blends_in_the_weighted_color() {
+        assert_eq!(
+            apply(
+                mix,
+                "AABBCC",
+                &[("with", Value::from("222222")), ("weight", Value::from(25))]
+            ),
+            "8895A2"
+        );
+        assert_eq!(
+            apply(mix, "AABBCC", &[("with", Value::from("FFFFFF"))]),
+            "D4DDE6"
+        );
+        assert!(mix(&Value::from("AABBCC"), &args(&[])).is_err());
+    }
+
+    #[test]
+    fn to_rgb_and_to_hsl_format_channels() {
+        assert_eq!(apply(to_rgb, "AABBCC", &[]), "170,187,204");
+        assert_eq!(apply(to_hsl, "#AABBCC", &[]), "210,25%,73%");
+    }
+
+    #[test]
+    fn invert_returns_the_negative() {
+        assert_eq!(apply(invert, "AABBCC", &[]), "554433");
+        assert_eq!(apply(invert, "#000000", &[]), "#FFFFFF");
+    }
+
+    #[test]
+    fn contrast_with_rounds_the_wcag_ratio() {
+        assert_eq!(
+            apply(contrast_with, "222222", &[("color", Value::from("AABBCC"))]),
+            8.1
+        );
+        assert_eq!(
+            apply(
+                contrast_with,
+                "FFFFFF",
+                &[("color", Value::from("#000000"))]
+            ),
+            21.0
+        );
+    }
+
+    #[test]
+    fn filters_are_also_functions() {
+        let mut tera = Tera::default();
+        register(&mut tera);
+        let rendered = tera
+            .render_str(
+                "{{ mix(color=\"AABBCC\", with=\"222222\", weight=25) }} {{ to_hsl(color=\"AABBCC\") }}",
+                &tera::Context::new(),
+            )
+            .unwrap();
+        assert_eq!(rendered, "8895A2 210,25%,73%");
+        let ratio = tera
+            .render_str(
+                "{{ contrast_with(color=\"222222\", with=\"AABBCC\") }}",
+                &tera::Context::new(),
+            )
+            .unwrap();
+        assert_eq!(ratio, "8.1");
+        assert!(
+            tera.render_str("{{ invert() }}", &tera::Context::new())
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! Runs user hook commands after outputs are written, e.g. to reload apps that read the generated configs.
+//!
+//! Hooks run through `sh -c` with a timeout. A failing or hanging hook is reported but never aborts the run. Their stdout is sent to stderr so Wallrust's own stdout stays clean.
+use log::{debug, info, warn};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a running hook is checked for completion.
+const HOOK_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Runs `command` through the shell, killing it after `timeout`, and reports how it exited.
+///
+/// Returns true if the hook exited successfully.
+pub fn run_hook(command: &str, timeout: Duration) -> bool {
+    info!("Running hook: {}", command);
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command).stdin(Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsFd;
+        if let Ok(stderr) = std::io::stderr().as_fd().try_clone_to_owned() {
+            cmd.stdout(Stdio::from(stderr));
+        }
+    }
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Failed to start hook `{}`: {}", command, e);
+            return false;
+        }
+    };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => {
+                debug!("Hook `{}` finished successfully", command);
+                return true;
+            }
+            Ok(Some(status)) => {
+                warn!("Hook `{}` failed ({})", command, status);
+                return false;
+            }
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                warn!(
+                    "Hook `{}` timed out after {}s and was killed",
+                    command,
+                    timeout.as_secs()
+                );
+                return false;
+            }
+            Ok(None) => thread::sleep(HOOK_POLL_INTERVAL),
+            Err(e) => {
+                warn!("Failed to wait for hook `{}`: {}", command, e);
+                return false;
+            }
+        }
+    }
+}
+
+/// Runs each command in order with `run_hook`; failures are reported and the remaining hooks still run.
+pub fn run_hooks(commands: &[String], timeout: Duration) {
+    for command in commands {
+        run_hook(command, timeout);
+    }
+}
@@ -1,12 +1,16 @@
 //! Generates a modern HTML preview of the extracted color palette.
 //!
 //! This module provides functions to render a visually appealing, responsive HTML file that displays the palette's primary, text, and accent colors, suitable for quick visual inspection and sharing.
+use crate::atomic::Transaction;
 use crate::config::Palette;
-use std::fs;
 use std::path::Path;
 
 /// Generates a modern, responsive HTML preview of the palette for visual inspection and sharing.
-pub fn generate_html(palette: &Palette, output_path: &Path) -> anyhow::Result<()> {
+pub fn generate_html(
+    palette: &Palette,
+    output_path: &Path,
+    transaction: &mut Transaction,
+) -> anyhow::Result<()> {
     let html = format!(
         r#"<!DOCTYPE html>
 <html lang="en">
@@ -135,6 +139,21 @@ pub fn generate_html(palette: &Palette, output_path: &Path) -> anyhow::Result<()
             flex-direction: column;
             align-items: center;
         }}
+        .contrast-block {{
+            border-radius: 8px;
+            width: 160px;
+            height: 110px;
+            padding: 15px;
+            display: flex;
+            flex-direction: column;
+            align-items: center;
+            justify-content: center;
+            box-shadow: 0 4px 10px rgba(0, 0, 0, 0.3);
+        }}
+        .contrast-sample {{
+            font-size: 1.6em;
+            font-weight: bold;
+        }}
         .accent-group {{
             margin-bottom: 2em;
         }}
@@ -321,6 +340,13 @@ pub fn generate_html(palette: &Palette, output_path: &Path) -> anyhow::Result<()
             <div class="palette" id="text-palette">
                 {}
             </div>
+
+            <div class="section-title">
+                <h2>Text Contrast</h2>
+            </div>
+            <div class="palette" id="contrast-palette">
+                {}
+            </div>
         </div>
 
         <div class="palette-section">
@@ -337,10 +363,11 @@ pub fn generate_html(palette: &Palette, output_path: &Path) -> anyhow::Result<()
         palette.primary.len(),
         generate_color_blocks(&palette.primary, "primary-block", "Primary"),
         generate_color_blocks(&palette.text, "color-block", "Text"),
+        generate_contrast_blocks(palette),
         generate_grouped_accent_blocks(&palette.accents)
     );
 
-    fs::write(output_path, html)?;
+    transaction.write(output_path, html.as_bytes())?;
     Ok(())
 }
 
@@ -368,6 +395,34 @@ fn generate_color_blocks(colors: &[String], class: &str, prefix: &str) -> String
         .join("\n")
 }
 
+/// Generates HTML blocks rendering each text color on its primary, labeled with the WCAG contrast ratio.
+fn generate_contrast_blocks(palette: &Palette) -> String {
+    palette
+        .primary
+        .iter()
+        .zip(&palette.text)
+        .enumerate()
+        .map(|(i, (pry, txt))| {
+            let ratio = palette
+                .text_contrast
+                .get(i)
+                .map_or_else(|| "n/a".to_string(), |r| format!("{:.2}:1", r));
+            format!(
+                r#"<div class="contrast-block" style="background-color: #{}; color: #{}" title="Text {} on Primary {}">
+                    <span class="contrast-sample">Aa</span>
+                    <span class="color-value">{}</span>
+                </div>"#,
+                pry,
+                txt,
+                i + 1,
+                i + 1,
+                ratio
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
 /// Generates grouped HTML blocks for accent colors, organized by primary color.
 fn generate_grouped_accent_blocks(accents: &[Vec<String>]) -> String {
     accents
@@ -1,10 +1,13 @@
 //! Wraps all ImageMagick CLI calls for color extraction, image processing, and color manipulation.
 //!
-//! This module provides functions to extract k-means colors, check brightness and saturation, modulate and convert colors, and generate thumbnails, all via the ImageMagick command-line interface.
+//! This module provides functions to extract k-means colors, check image brightness and saturation, and generate thumbnails, all via the ImageMagick command-line interface, plus the `MagickBackend` implementation of `ColorBackend` built on them.
+use crate::backend::{BackendKind, ColorBackend};
 use crate::error::WallbashError;
 use lazy_static::lazy_static;
+use log::{debug, warn};
 use regex::Regex;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Output, Stdio};
 
 lazy_static! {
@@ -14,10 +17,6 @@ lazy_static! {
 
     
     static ref FX_MEAN_RE: Regex = Regex::new(r"^[0-9.eE+-]+$").unwrap();
-
-    
-    
-    static ref HSB_RE: Regex = Regex::new(r"hsb\((\d+\.?\d*),").unwrap();
 }
 
 /// Runs the ImageMagick `magick` command with the given arguments, handling errors and warnings.
@@ -70,6 +69,21 @@ impl OutputExt for Output {
     }
 }
 
+/// Returns the installed ImageMagick version, e.g. "ImageMagick 7.1.1-29 Q16-HDRI".
+pub fn magick_version() -> Result<String, WallbashError> {
+    let stdout = run_magick(&["-version"])?.stdout_str()?;
+    let first_line = stdout.lines().next().unwrap_or_default();
+    let version = first_line
+        .strip_prefix("Version:")
+        .unwrap_or(first_line)
+        .trim();
+    Ok(version
+        .split_whitespace()
+        .take(3)
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
 pub fn ping_image(image_path: &Path) -> Result<(), WallbashError> {
     let path_str = image_path.to_str().ok_or_else(|| {
         WallbashError::InvalidInput(format!("Invalid path characters: {}", image_path.display()))
@@ -132,7 +146,7 @@ pub fn extract_kmeans_colors(
         }
     }
 
-    dcol_raw.sort_by(|a, b| b.0.cmp(&a.0));
+    dcol_raw.sort_by_key(|(count, _)| std::cmp::Reverse(*count));
 
     Ok(dcol_raw)
 }
@@ -151,7 +165,10 @@ pub fn check_brightness_dark(target: &str) -> Result<bool, WallbashError> {
     let mean_str = fx_output.stdout_str()?.trim().to_string();
     if !FX_MEAN_RE.is_match(&mean_str) {
         if mean_str.contains("undefined") {
-            eprintln!("Warning: fx:mean brightness calculation returned undefined for target '{}'. Assuming light.", target);
+            warn!(
+                "fx:mean brightness calculation returned undefined for target '{}'. Assuming light.",
+                target
+            );
             return Ok(false);
         }
         return Err(WallbashError::MagickParse(format!(
@@ -184,7 +201,7 @@ pub fn get_average_saturation(mpc_path: &Path) -> Result<f64, WallbashError> {
     let mean_str = fx_output.stdout_str()?.trim().to_string();
     if !FX_MEAN_RE.is_match(&mean_str) {
         if mean_str.contains("undefined") {
-            eprintln!("Warning: fx:mean saturation calculation returned undefined. Assuming not grayscale.");
+            warn!("fx:mean saturation calculation returned undefined. Assuming not grayscale.");
             return Ok(1.0);
         }
         return Err(WallbashError::MagickParse(format!(
@@ -196,96 +213,6 @@ pub fn get_average_saturation(mpc_path: &Path) -> Result<f64, WallbashError> {
     Ok(mean)
 }
 
-/// Modulates the brightness, saturation, and hue of a color using ImageMagick, returning the resulting hex color.
-pub fn modulate_color(
-    source_target: &str,
-    bri: u8,
-    sat: u8,
-    hue: u8,
-) -> Result<String, WallbashError> {
-    let modulate_arg = format!("{},{},{}", bri, sat, hue);
-    let mod_output = run_magick(&[
-        source_target,
-        "-depth",
-        "8",
-        "-normalize",
-        "-modulate",
-        &modulate_arg,
-        "-depth",
-        "8",
-        "-format",
-        "%c",
-        "histogram:info:",
-    ])?;
-
-    let hex = HISTOGRAM_RE
-        .captures(&mod_output.stdout_str()?)
-        .and_then(|cap| cap.get(2))
-        .map(|m| m.as_str().to_uppercase())
-        .ok_or_else(|| {
-            WallbashError::MagickParse(format!(
-                "Could not parse modulated color from: {}",
-                mod_output.stdout_str().unwrap_or_default()
-            ))
-        })?;
-    Ok(hex)
-}
-
-/// Extracts the hue value from a color target using ImageMagick in HSB colorspace.
-///
-/// Parses the output of a histogram info command to find the hue component.
-pub fn get_hsb_hue(color_target: &str) -> Result<String, WallbashError> {
-    let hue_output = run_magick(&[
-        color_target,
-        "-colorspace",
-        "HSB",
-        "-format",
-        "%c",
-        
-        "histogram:info:",
-    ])?;
-
-    
-    let output_string = hue_output.stdout_str()?;
-
-    let hue_str = HSB_RE
-        .captures(&output_string)
-        .and_then(|cap| cap.get(1))
-        .map(|m| m.as_str())
-        .ok_or_else(|| {
-            WallbashError::MagickParse(format!("Could not parse HSB Hue from: {}", output_string))
-        })?;
-    Ok(hue_str.to_string())
-}
-
-/// Converts an HSB string (e.g., "hsb(210,80%,60%)") to a hex color using ImageMagick.
-///
-/// Returns the resulting hex color, or an error if parsing fails.
-pub fn color_from_hsb(hsb_string: &str) -> Result<String, WallbashError> {
-    let hsb_target = format!("xc:{}", hsb_string);
-    let acol_output = run_magick(&[
-        &hsb_target,
-        "-depth",
-        "8",
-        "-format",
-        "%c",
-        "histogram:info:",
-    ])?;
-
-    let hex = HISTOGRAM_RE
-        .captures(&acol_output.stdout_str()?)
-        .and_then(|cap| cap.get(2))
-        .map(|m| m.as_str().to_uppercase())
-        .ok_or_else(|| {
-            WallbashError::MagickParse(format!(
-                "Could not parse color from HSB ({}) output: {}",
-                hsb_string,
-                acol_output.stdout_str().unwrap_or_default()
-            ))
-        })?;
-    Ok(hex)
-}
-
 /// Generates a thumbnail image for color extraction, resizing and centering the input image.
 pub fn generate_thumbnail(
     input_path: &Path,
@@ -305,7 +232,7 @@ pub fn generate_thumbnail(
         WallbashError::InvalidInput(format!("Invalid path characters: {}", thumbnail_path.display()))
     })?;
 
-    println!("Generating thumbnail: {} -> {}", input_str, thumbnail_str);
+    debug!("Generating thumbnail: {} -> {}", input_str, thumbnail_str);
 
     
     
@@ -322,8 +249,10 @@ pub fn generate_thumbnail(
        .arg("90")
        .arg(thumbnail_str);
 
-    println!("Running command: magick {}[0] -strip -resize 1000 -gravity center -extent 1000 -quality 90 {}", 
-             input_str, thumbnail_str);
+    debug!(
+        "Running command: magick {}[0] -strip -resize 1000 -gravity center -extent 1000 -quality 90 {}",
+        input_str, thumbnail_str
+    );
 
     let output = cmd.output()?;
     
@@ -335,6 +264,79 @@ pub fn generate_thumbnail(
         )));
     }
 
-    println!("Successfully generated thumbnail: {}", thumbnail_path.display());
+    debug!(
+        "Successfully generated thumbnail: {}",
+        thumbnail_path.display()
+    );
     Ok(())
 }
+
+/// `ColorBackend` implementation that runs every operation through the `magick` CLI.
+///
+/// The loaded image is converted to a temporary MPC cache, which is removed when the backend is dropped.
+pub struct MagickBackend {
+    mpc_path: PathBuf,
+}
+
+impl MagickBackend {
+    pub fn new(mpc_path: PathBuf) -> Self {
+        Self { mpc_path }
+    }
+
+    fn mpc_target(&self) -> String {
+        format!("mpc:{}", self.mpc_path.display())
+    }
+}
+
+impl Drop for MagickBackend {
+    fn drop(&mut self) {
+        match fs::remove_file(&self.mpc_path) {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!(
+                "Failed to remove temp MPC file {}: {}",
+                self.mpc_path.display(),
+                e
+            ),
+        }
+    }
+}
+
+impl ColorBackend for MagickBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Magick
+    }
+
+    fn version(&self) -> String {
+        magick_version().unwrap_or_else(|_| "unknown".to_string())
+    }
+
+    fn load_image(&mut self, image_path: &Path) -> Result<(), WallbashError> {
+        ping_image(image_path)?;
+        create_mpc_cache(image_path, &self.mpc_path)
+    }
+
+    fn extract_kmeans_colors(
+        &self,
+        colors: usize,
+        fuzz: u8,
+    ) -> Result<Vec<(u64, String)>, WallbashError> {
+        extract_kmeans_colors(&self.mpc_path, colors, fuzz)
+    }
+
+    fn is_image_dark(&self) -> Result<bool, WallbashError> {
+        check_brightness_dark(&self.mpc_target())
+    }
+
+    fn average_saturation(&self) -> Result<f64, WallbashError> {
+        get_average_saturation(&self.mpc_path)
+    }
+
+    fn generate_thumbnail(
+        &self,
+        input_path: &Path,
+        thumbnail_path: &Path,
+    ) -> Result<(), WallbashError> {
+        generate_thumbnail(input_path, thumbnail_path)
+    }
+}
@@ -0,0 +1,26 @@
+//! Sets up Wallrust's log output on stderr.
+//!
+//! Progress and diagnostics go through the `log` macros so stdout stays free for machine-readable output such as a printed palette. `-q` keeps only errors, `--verbose` adds details, and passing it twice logs everything.
+use log::{Level, LevelFilter};
+use std::io::Write;
+
+/// Installs the stderr logger for the given `-q`/`--verbose` flags.
+pub fn init(quiet: bool, verbose: u8) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        // Dependencies only get to report problems.
+        .filter_level(level.min(LevelFilter::Warn))
+        .filter_module(module_path!().split("::").next().unwrap_or_default(), level)
+        .format(|buf, record| match record.level() {
+            Level::Error => writeln!(buf, "Error: {}", record.args()),
+            Level::Warn => writeln!(buf, "Warning: {}", record.args()),
+            _ => writeln!(buf, "{}", record.args()),
+        })
+        .target(env_logger::Target::Stderr)
+        .init();
+}
@@ -3,25 +3,59 @@
 //! ## Usage
 //! ```text
 //! Usage: wallrust [OPTIONS] [INPUT_IMAGE]
+//!        wallrust <COMMAND>
+//!
+//! Commands:
+//!   extract  Extract a palette from an image and write only wallrust.json
+//!   render   Apply templates and write outputs from an existing palette JSON file
+//!   cache    Inspect or clean the palette cache (list, clear, prune)
+//!   preview  Show a palette in the terminal or as an HTML page
+//!   detect   Print the current Hyprland wallpaper path
+//!   watch    Re-theme whenever the given wallpaper file or symlink, or the Hyprland wallpaper, changes
 //!
 //! Arguments:
 //!   [INPUT_IMAGE]  
 //!
 //! Options:
 //!   -f, --force             
-//!   -o, --output-dir <DIR>  
+//!       --strict-hash       Always hash the image instead of trusting unchanged size, mtime, and inode
+//!       --no-strict-hash    Trust unchanged size, mtime, and inode, even if the config enables strict hashing
 //!   -v, --vibrant           Use vibrant color profile
 //!   -p, --pastel            Use pastel color profile
 //!   -m, --mono              Use monochrome color profile
 //!   -c, --custom <CURVE>    Use custom color curve (provide curve string)
+//!       --profile <NAME>    Use a named color profile from the config file or a built-in one (default, vibrant, pastel, mono)
 //!   -d, --dark              Force dark sort mode
 //!   -l, --light             Force light sort mode
+//!       --auto              Pick dark or light sort mode from the image, overriding the config's mode
 //!       --colors <COLORS>   Number of primary colors to extract [default: 4]
 //!       --fuzz <FUZZ>       Color fuzziness percentage for k-means [default: 70]
 //!       --detect-hyprland   Attempt to detect current Hyprland wallpaper via hyprctl
-//!       --html              Generate HTML color palette preview
 //!       --wallset           Generate thumbnails and dcol files compatible with wallbash scripts
+//!       --no-wallset        Don't use wallset mode, even if the config enables it
+//!       --backend <BACKEND> Color extraction backend [default: magick] [possible values: magick, native]
+//!       --colorspace <COLORSPACE>  Color space for sorting and accent/text derivation [default: hsb] [possible values: hsb, oklch]
+//!       --min-contrast <RATIO>     Minimum WCAG contrast ratio of each text color against its primary (0 disables) [default: 4.5]
+//!       --dry-run           Show each output that would be created or modified, with a diff on stderr, without writing any file or the cache
+//!       --print <FORMAT>    Print the palette to stdout in this format [possible values: json, dcol, css, env]
+//!       --no-write          Don't write output files, templates, or the HTML preview, and run no hooks
+//!       --apply-terminals   Recolor open terminals via escape sequences and save them to the cache for new shells
+//!       --emit <APPS>       Also write built-in color configs for these apps (a same-named template overrides one)
+//!                           [possible values: kitty, alacritty, foot, wezterm, rofi, dunst, waybar, hyprland, base16, base24]
+//!       --pywal-compat      Also write pywal's colors.json, colors, colors.sh, and colors.Xresources to ~/.cache/wal
+//!       --swatches <FORMATS>  Also write the palette as swatches for design tools (wallrust.gpl, .ase, .aco)
+//!                           [possible values: gpl, ase, aco]
+//!   -o, --output-dir <DIR>
+//!       --html              Generate HTML color palette preview
+//!       --no-html           Don't generate the HTML preview, even if the config enables it
 //!       --no-templates      Skip custom template generation
+//!       --templates         Generate custom templates, even if the config skips them
+//!       --from-palette <FILE>  Write outputs from a palette JSON file instead of extracting from an image
+//!       --from-dcol <FILE>     Write outputs from a dcol file instead of extracting from an image
+//!       --from-base16 <FILE>   Write outputs from a Base16 or Base24 YAML scheme instead of extracting from an image
+//!       --config <PATH>     Read defaults from this config file instead of ~/.config/wallrust/config.toml
+//!   -q, --quiet             Only log errors, keeping stdout for machine-readable output
+//!       --verbose...        Log more details on stderr (--verbose --verbose for everything)
 //!   -h, --help              Print help
 //!   -V, --version           Print version
 //! ```
@@ -39,13 +73,68 @@
 //!
 //! # Extract from the current Hyprland wallpaper, apply a pastel curve, and skip template generation
 //! wallrust --detect-hyprland --pastel --no-templates
+//!
+//! # Extract without ImageMagick installed, using the built-in Rust backend
+//! wallrust ~/Pictures/wallpaper.jpg --backend native
+//!
+//! # Extract only the palette JSON, tweak it, then re-render every template from it
+//! wallrust extract ~/Pictures/wallpaper.jpg -o /tmp/palette
+//! wallrust render --palette /tmp/palette/wallrust.json
+//!
+//! # See what would change in every output (as unified diffs) without touching any file
+//! wallrust ~/Pictures/wallpaper.jpg --dry-run
+//!
+//! # Load the palette into shell variables ($WALLRUST_PRY1, $WALLRUST_COLOR4, ...) without writing files
+//! eval "$(wallrust ~/Pictures/wallpaper.jpg --print env --no-write -q)"
+//!
+//! # Write ready-made color configs for kitty and waybar without any templates
+//! wallrust ~/Pictures/wallpaper.jpg --emit kitty,waybar
+//!
+//! # Bring the palette into GIMP/Inkscape and Adobe apps as named swatches
+//! wallrust ~/Pictures/wallpaper.jpg --swatches gpl,ase,aco
+//!
+//! # Keep pywal-based tools (pywalfox, xrdb setups, ...) working by also writing ~/.cache/wal
+//! wallrust ~/Pictures/wallpaper.jpg --pywal-compat
+//!
+//! # Re-render templates and the HTML preview from a hand-tuned dcol file
+//! wallrust --from-dcol ~/themes/tuned.dcol --html
+//!
+//! # Export a base16 scheme for tinted-theming builders, or theme everything from an existing one
+//! wallrust ~/Pictures/wallpaper.jpg --emit base16
+//! wallrust --from-base16 ~/schemes/tomorrow-night.yaml
+//!
+//! # Show the cached palette in the terminal, or drop stale cache entries
+//! wallrust preview
+//! wallrust cache prune
+//!
+//! # Keep the theme in sync with a wallpaper symlink managed by another tool
+//! wallrust watch ~/.current_wallpaper --backend native
 //! ```
 //!
 //! ## Advanced
 //! Wallrust supports custom color curves, wallset mode (hash-based palette extraction for theme switching), palette caching, and automatic dark/light mode detection. It can generate and place files anywhere, with optional backup of previous configs. Integrates with Hyprland for automatic wallpaper detection and can be scripted for dynamic theme automation.
 //!
+//! ## Config File
+//! Defaults for any option can be set in `~/.config/wallrust/config.toml` (or a file passed with `--config`). Options given on the command line always win; boolean options can be turned off with their negation, e.g. `--no-html`.
+//!
+//! ```toml
+//! colors = 6
+//! profile = "pastel"   # default, vibrant, pastel, mono (or `curve = "..."`)
+//! mode = "dark"        # auto, dark, light
+//! backend = "native"
+//! html = true
+//!
+//! [cache]
+//! max_entries = 256    # palette cache size, least recently used entries evicted first
+//! max_size_mb = 32
+//!
+//! [profiles.nord-ish]
+//! curve = [[20, 40], [28, 36], [36, 32], [44, 28], [52, 26], [60, 24], [70, 22], [82, 18], [94, 12]]
+//! pry_dark_bri = 110   # optional modulate overrides (pry_*/txt_*)
+//! ```
+//!
 //! ## Templating
-//! Place [Tera](https://tera.netlify.app/) templates in `~/.config/wallrust/templates/` to generate any config file with palette variables (primary, text, accents, etc). Output paths and backup behavior can be controlled via template directives at the top of each template. This enables fully automated, wallpaper-driven config generation for any app.
+//! Place [Tera](https://tera.netlify.app/) templates in `~/.config/wallrust/templates/` to generate any config file with palette variables (primary, text, accents, etc). Subfolders are mirrored into the output directory, and `_`-prefixed files are partials for `include`/`extends` that are not rendered themselves. Output paths, backup behavior, and reload hooks (`{# hook: pkill -USR1 kitty #}`) can be controlled via template directives at the top of each template; `post_hooks` in the config file run after all outputs. This enables fully automated, wallpaper-driven config generation for any app.
 //!
 //! ## Available Tera Template Variables
 //! The following variables are available in your templates:
@@ -59,6 +148,9 @@
 //! - `text_rgba`: Array of RGBA strings for each text color
 //! - `accents_rgba`: 2D array of RGBA strings for each accent color
 //! - `is_dark`: Boolean, true if mode is dark
+//! - `text_contrast`: WCAG contrast ratio of each text color against its primary
+//! - `ansi`: 16-color terminal palette (`ansi.color0` to `ansi.color15`) plus `ansi.foreground`, `ansi.background`, `ansi.cursor`, and `ansi.selection`
+//! - `base00` to `base0F`: the palette as a Base16 scheme, plus `base10` to `base17` for Base24
 //!
 //! Example usage in a Tera template:
 //!
@@ -69,265 +161,126 @@
 //! ```
 //! 
 
+mod ansi;
+mod atomic;
+mod backend;
+mod base16;
 mod cache;
 mod cli;
+mod color;
+mod commands;
 mod config;
 mod error;
+mod filters;
+mod hooks;
 mod html;
 mod imagemagick;
+mod logging;
+mod native;
 mod output;
 mod palette;
+mod preview;
+mod pywal;
+mod swatches;
+mod terminals;
 mod wallpaper;
+mod watch;
+mod writers;
 
 use anyhow::{Context, Result};
-use clap::Parser;
-use config::AppPaths;
-use error::WallbashError;
-use std::fs;
-use std::path::{PathBuf, Path};
-use sha2::{Sha256, Digest};
-
-
-fn calculate_hash(path: &Path) -> Result<String, WallbashError> {
-    let path_str = path.to_str().ok_or_else(|| {
-        WallbashError::InvalidInput(format!("Invalid path characters: {}", path.display()))
-    })?;
-    
-    let mut hasher = Sha256::new();
-    hasher.update(path_str.as_bytes());
-    let result = hasher.finalize();
-    
-    Ok(format!("{:x}", result))
-}
-
-
-fn log_palette_preview(palette: &config::Palette, source: &str) {
-    println!("----- {} Palette Preview -----", source);
-    println!("Mode: {}", palette.mode);
-    
-    
-    for i in 0..std::cmp::min(3, palette.primary.len()) {
-        println!("Primary {}: {}", i+1, palette.primary[i]);
-    }
-    
-    
-    if let Some(accents) = palette.accents.first() {
-        if let Some(accent) = accents.first() {
-            println!("First Accent: {}", accent);
-        }
-    }
-    println!("-------------------------------");
-}
+use clap::{CommandFactory, FromArgMatches};
+use cli::Command;
+use commands::PaletteFile;
+use std::time::Duration;
 
 fn main() -> Result<()> {
-    let cli = cli::Cli::parse();
-
-    let input_image_path = match cli.input_image {
-        Some(path) => PathBuf::from(
-            shellexpand::full(&path)
-                .map_err(|e| {
-                    WallbashError::PathExpansion(format!(
-                        "Input image path expansion failed: {}",
-                        e
-                    ))
-                })?
-                .into_owned(),
-        ),
-        None if cli.detect_hyprland => {
-            wallpaper::detect_hyprland_wallpaper().context("Failed wallpaper detection")?
-        }
-        None => {
-            anyhow::bail!("No input image provided and --detect-hyprland not specified.");
-        }
+    let matches = cli::Cli::command().get_matches();
+    let mut cli = cli::Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    logging::init(cli.quiet, cli.verbose);
+    let user_config =
+        config::UserConfig::load(cli.config.as_deref()).context("Failed to load config file")?;
+    cli.apply_config(&user_config, &matches);
+    let output_options = |skip_templates, args: &cli::OutputArgs| output::OutputOptions {
+        skip_templates,
+        hook_timeout: user_config.hook_timeout(),
+        dry_run: args.write.dry_run,
+        print: args.write.print,
+        no_write: args.write.no_write,
+        apply_terminals: args.apply_terminals,
+        emit: args.emit.clone(),
+        pywal_compat: args.pywal_compat,
+        swatches: args.swatches.clone(),
     };
 
-    if !input_image_path.is_file() {
-        anyhow::bail!(WallbashError::InvalidInput(format!(
-            "Input image file not found or not a file: {}",
-            input_image_path.display()
-        )));
-    }
-    println!("Using wallpaper: {}", input_image_path.display());
-
-    let app_paths =
-        AppPaths::new(cli.output_dir).context("Failed to initialize application paths")?;
-    let color_profile =
-        config::ColorProfile::from_cli(cli.vibrant, cli.pastel, cli.mono, cli.custom)
-            .context("Invalid color profile selection")?;
-    let initial_sort_mode =
-        config::SortMode::from_cli(cli.dark, cli.light).context("Invalid sort mode selection")?;
-
-    let extraction_image_path;
-    let file_hash;
-    
-    if cli.wallset {
-        
-        file_hash = calculate_hash(&input_image_path)?;
-        
-        
-        app_paths.ensure_thumbs_dir()?;
-        
-        let thumbnail_path = app_paths.thumbs_dir.join(format!("{}.thmb", file_hash));
-        println!("Thumbnail path: {}", thumbnail_path.display());
-        
-        if !thumbnail_path.exists() || cli.force {
-            println!("Generating thumbnail for color extraction...");
-            imagemagick::generate_thumbnail(&input_image_path, &thumbnail_path)
-                .context("Failed to generate thumbnail")?;
-        } else {
-            println!("Using existing thumbnail: {}", thumbnail_path.display());
-        }
-        extraction_image_path = thumbnail_path;
-    } else {
-        
-        extraction_image_path = input_image_path.clone();
-        file_hash = String::new(); 
-    }
-
-    
-    let should_force = if cli.force {
-        println!("Force flag set, skipping cache check.");
-        true
-    } else if cli.wallset {
-        println!("Checking if cache needs regeneration for wallset mode...");
-        false
-    } else {
-        false
-    };
-    
-    let cached_palette = if should_force {
-        None
-    } else {
-        cache::needs_regeneration(
-            &app_paths.wallbash_cache_file,
-            &input_image_path,
-            &color_profile,
-            initial_sort_mode,
-            cli.wallset
-        )?
+    let from_file = match (&cli.from_palette, &cli.from_dcol, &cli.from_base16) {
+        (Some(file), _, _) => Some(PaletteFile::Json(file)),
+        (None, Some(file), _) => Some(PaletteFile::Dcol(file)),
+        (None, None, Some(file)) => Some(PaletteFile::Base16(file)),
+        (None, None, None) => None,
     };
 
-    let final_palette = match cached_palette {
-        Some(palette) => {
-            if cli.wallset {
-                println!("Using cached palette (from wallset mode)");
-            } else {
-                println!("Using cached palette (from regular mode)");
-            }
-            log_palette_preview(&palette, "Cached");
-            palette
+    let mut templates = output::Templates::default();
+    match cli.command {
+        None => match from_file {
+            Some(palette_file) => commands::render(
+                palette_file,
+                &user_config,
+                cli.output_dir,
+                cli.html,
+                &output_options(cli.no_templates, &cli.output),
+                &mut templates,
+            ),
+            None => commands::run(
+                &cli.extract,
+                &user_config,
+                cli.output_dir,
+                cli.html,
+                &output_options(cli.no_templates, &cli.output),
+                &mut templates,
+            ),
         },
-        None => {
-            println!(
-                "Generating new palette (Profile: {}, Mode: {}, Colors: {}, Fuzz: {}, Wallset: {})...",
-                color_profile, initial_sort_mode, cli.colors, cli.fuzz, cli.wallset
-            );
-
-            
-            imagemagick::ping_image(&extraction_image_path).context("ImageMagick ping failed")?;
-
-            /// Helper struct to ensure temporary MPC cache files are cleaned up after palette extraction.
-            struct CleanupGuard<'a>(&'a PathBuf);
-            impl<'a> Drop for CleanupGuard<'a> {
-                fn drop(&mut self) {
-                    match fs::remove_file(self.0) {
-                        Ok(_) => {}
-                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
-                        Err(e) => eprintln!(
-                            "Warning: Failed to remove temp MPC file {}: {}",
-                            self.0.display(),
-                            e
-                        ),
-                    }
-                }
-            }
-            let mpc_path = app_paths.mpc_cache_file.clone();
-            let _cleanup_guard = CleanupGuard(&mpc_path);
-
-            
-            imagemagick::create_mpc_cache(&extraction_image_path, &app_paths.mpc_cache_file)
-                .context("Failed to create ImageMagick MPC cache")?;
-
-            let mut base_colors_raw =
-                imagemagick::extract_kmeans_colors(&app_paths.mpc_cache_file, cli.colors, cli.fuzz)
-                    .context("Failed to extract k-means colors")?;
-
-            if base_colors_raw.len() < cli.colors {
-                println!(
-                    "RETRYING K-Means: Found {} colors, need {}. Requesting {}.",
-                    base_colors_raw.len(),
-                    cli.colors,
-                    cli.colors + 2
-                );
-                base_colors_raw = imagemagick::extract_kmeans_colors(
-                    &app_paths.mpc_cache_file,
-                    cli.colors + 2,
-                    cli.fuzz,
-                )
-                .context("Failed to extract k-means colors on retry")?;
-
-                if base_colors_raw.len() < cli.colors {
-                    anyhow::bail!(WallbashError::NotEnoughColors {
-                        required: cli.colors,
-                        found: base_colors_raw.len()
-                    });
-                }
-            }
-
-            let base_hex_colors: Vec<String> = base_colors_raw
-                .into_iter()
-                .map(|(_, hex)| hex)
-                .take(cli.colors)
-                .collect();
-
-            
-            let generated_palette = palette::generate_palette(
-                &input_image_path, 
-                &app_paths.mpc_cache_file,
-                base_hex_colors,
-                cli.colors,
-                &color_profile,
-                initial_sort_mode,
-            )
-            .context("Failed to generate full palette")?;
-
-            let cache_data = cache::create_cache_data(
-                &input_image_path,
-                &color_profile,
-                initial_sort_mode,
-                &generated_palette,
-                cli.wallset
-            )?;
-            cache::write_cache(&app_paths.wallbash_cache_file, &cache_data)
-                .context("Failed to write palette cache")?;
-
-            
-            log_palette_preview(&generated_palette, if cli.wallset { "Thumbnail" } else { "Original" });
-            generated_palette
+        Some(Command::Extract { extract, write }) => {
+            commands::extract(&extract, &write, &user_config, cli.output_dir)
         }
-    };
-
-    
-    output::generate_outputs(&final_palette, &app_paths, cli.no_templates)
-        .context("Failed to generate output files")?;
-    
-    
-    if cli.wallset && !file_hash.is_empty() {
-        
-        app_paths.ensure_dcols_dir()?;
-        
-        let dcol_path = app_paths.dcols_dir.join(format!("{}.dcol", file_hash));
-        output::write_dcol(&final_palette, &dcol_path)
-            .context("Failed to write dcol file to hashed path")?;
-    }
-        
-    if cli.html {
-        let html_path = app_paths.output_dir.join("palette.html");
-        html::generate_html(&final_palette, &html_path)
-            .context("Failed to generate HTML preview")?;
-        println!("Generated HTML preview at: {}", html_path.display());
+        Some(Command::Render {
+            palette,
+            html,
+            no_templates,
+            output,
+            ..
+        }) => commands::render(
+            PaletteFile::Json(&palette),
+            &user_config,
+            cli.output_dir,
+            html,
+            &output_options(no_templates, &output),
+            &mut templates,
+        ),
+        Some(Command::Cache { action }) => commands::cache(action, &user_config),
+        Some(Command::Preview {
+            palette,
+            html,
+            color,
+        }) => commands::preview(palette.as_deref(), cli.output_dir, html, color),
+        Some(Command::Detect) => commands::detect(),
+        Some(Command::Watch {
+            extract,
+            interval,
+            debounce,
+            html,
+            no_templates,
+            output,
+            ..
+        }) => watch::watch(
+            &extract,
+            &user_config,
+            &watch::WatchOptions {
+                interval: Duration::from_millis(interval),
+                debounce: Duration::from_millis(debounce),
+                output_dir: cli.output_dir,
+                html,
+                output: output_options(no_templates, &output),
+            },
+        ),
     }
-
-    println!("Wallbash finished successfully.");
-    Ok(())
 }
@@ -0,0 +1,277 @@
+//! Pure-Rust color extraction backend that decodes images and runs k-means in-process.
+//!
+//! This module provides `NativeBackend`, a `ColorBackend` implementation that needs no external binaries. Whole-image statistics use the same ImageMagick-compatible formulas as the `color` module so that both backends produce comparable palettes.
+use crate::backend::{BackendKind, ColorBackend};
+use crate::color::{gray, rgb_to_hsl, to_hex};
+use crate::error::WallbashError;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{ImageReader, Rgb, RgbImage};
+use log::debug;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Longest edge, in pixels, of the downsampled copy used for analysis.
+const ANALYSIS_SIZE: u32 = 256;
+const KMEANS_MAX_ITERATIONS: usize = 100;
+/// Relative distortion change below which k-means is considered converged at 100% fuzz.
+const KMEANS_TOLERANCE: f64 = 0.01;
+const THUMBNAIL_SIZE: u32 = 1000;
+const THUMBNAIL_QUALITY: u8 = 90;
+
+/// `ColorBackend` implementation that performs all work in-process.
+#[derive(Default)]
+pub struct NativeBackend {
+    pixels: Vec<[f64; 3]>,
+}
+
+impl NativeBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads already decoded and downsampled pixels for the whole-image queries.
+    fn load_pixels(&mut self, image: &RgbImage) -> Result<(), WallbashError> {
+        self.pixels = image
+            .pixels()
+            .map(|Rgb([r, g, b])| [*r as f64 / 255.0, *g as f64 / 255.0, *b as f64 / 255.0])
+            .collect();
+        self.ensure_loaded()
+    }
+
+    fn ensure_loaded(&self) -> Result<(), WallbashError> {
+        if self.pixels.is_empty() {
+            return Err(WallbashError::InvalidInput(
+                "No image loaded into the native backend".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn open_image(image_path: &Path) -> Result<image::DynamicImage, WallbashError> {
+    Ok(ImageReader::open(image_path)?
+        .with_guessed_format()?
+        .decode()?)
+}
+
+fn distance_sq(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+/// Seeds centroids deterministically by splitting the pixels, ordered by gray level, into equal-count buckets.
+fn seed_centroids(pixels: &[[f64; 3]], k: usize) -> Vec<[f64; 3]> {
+    let mut sorted = pixels.to_vec();
+    sorted.sort_by(|a, b| gray(*a).total_cmp(&gray(*b)));
+    let bucket = sorted.len().div_ceil(k).max(1);
+    sorted
+        .chunks(bucket)
+        .map(|chunk| {
+            let mut sum = [0.0; 3];
+            for p in chunk {
+                sum[0] += p[0];
+                sum[1] += p[1];
+                sum[2] += p[2];
+            }
+            let n = chunk.len() as f64;
+            [sum[0] / n, sum[1] / n, sum[2] / n]
+        })
+        .collect()
+}
+
+/// Runs Lloyd's k-means over the pixels and returns (member count, centroid) pairs for non-empty clusters.
+fn kmeans(pixels: &[[f64; 3]], k: usize, tolerance: f64) -> Vec<(u64, [f64; 3])> {
+    let mut centroids = seed_centroids(pixels, k);
+    let mut assignments = vec![0usize; pixels.len()];
+    let mut previous_distortion = f64::MAX;
+
+    for _ in 0..KMEANS_MAX_ITERATIONS {
+        let mut distortion = 0.0;
+        for (pixel, assignment) in pixels.iter().zip(assignments.iter_mut()) {
+            let (index, dist) = centroids
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (i, distance_sq(pixel, c)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap_or((0, 0.0));
+            *assignment = index;
+            distortion += dist;
+        }
+
+        let mut sums = vec![[0.0; 3]; centroids.len()];
+        let mut counts = vec![0u64; centroids.len()];
+        for (pixel, &index) in pixels.iter().zip(assignments.iter()) {
+            sums[index][0] += pixel[0];
+            sums[index][1] += pixel[1];
+            sums[index][2] += pixel[2];
+            counts[index] += 1;
+        }
+        for ((centroid, sum), &count) in centroids.iter_mut().zip(&sums).zip(&counts) {
+            if count > 0 {
+                let n = count as f64;
+                *centroid = [sum[0] / n, sum[1] / n, sum[2] / n];
+            }
+        }
+
+        let change = (previous_distortion - distortion).abs() / distortion.max(f64::EPSILON);
+        previous_distortion = distortion;
+        if change <= tolerance {
+            break;
+        }
+    }
+
+    let mut counts = vec![0u64; centroids.len()];
+    for &index in &assignments {
+        counts[index] += 1;
+    }
+    counts
+        .into_iter()
+        .zip(centroids)
+        .filter(|(count, _)| *count > 0)
+        .collect()
+}
+
+impl ColorBackend for NativeBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Native
+    }
+
+    fn version(&self) -> String {
+        env!("CARGO_PKG_VERSION").to_string()
+    }
+
+    fn load_image(&mut self, image_path: &Path) -> Result<(), WallbashError> {
+        let image = open_image(image_path)?
+            .thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE)
+            .to_rgb8();
+        self.load_pixels(&image)
+    }
+
+    /// The `fuzz` percentage scales the k-means convergence tolerance.
+    fn extract_kmeans_colors(
+        &self,
+        colors: usize,
+        fuzz: u8,
+    ) -> Result<Vec<(u64, String)>, WallbashError> {
+        self.ensure_loaded()?;
+        let tolerance = KMEANS_TOLERANCE * f64::from(fuzz) / 100.0;
+
+        let mut dcol_raw: Vec<(u64, String)> = Vec::new();
+        for (count, centroid) in kmeans(&self.pixels, colors.max(1), tolerance) {
+            let hex = to_hex(centroid);
+            match dcol_raw.iter_mut().find(|(_, existing)| *existing == hex) {
+                Some(entry) => entry.0 += count,
+                None => dcol_raw.push((count, hex)),
+            }
+        }
+        dcol_raw.sort_by_key(|(count, _)| std::cmp::Reverse(*count));
+        Ok(dcol_raw)
+    }
+
+    fn is_image_dark(&self) -> Result<bool, WallbashError> {
+        self.ensure_loaded()?;
+        let mean = self.pixels.iter().map(|p| gray(*p)).sum::<f64>() / self.pixels.len() as f64;
+        Ok(mean < 0.5)
+    }
+
+    fn average_saturation(&self) -> Result<f64, WallbashError> {
+        self.ensure_loaded()?;
+        let total: f64 = self.pixels.iter().map(|p| rgb_to_hsl(*p).1).sum();
+        Ok(total / self.pixels.len() as f64)
+    }
+
+    /// Mirrors `magick IN[0] -resize 1000 -gravity center -extent 1000 -quality 90 OUT` on a white background.
+    fn generate_thumbnail(
+        &self,
+        input_path: &Path,
+        thumbnail_path: &Path,
+    ) -> Result<(), WallbashError> {
+        if let Some(parent) = thumbnail_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        debug!(
+            "Generating thumbnail: {} -> {}",
+            input_path.display(),
+            thumbnail_path.display()
+        );
+
+        let source = open_image(input_path)?.to_rgb8();
+        let height = ((source.height() as f64 * THUMBNAIL_SIZE as f64 / source.width() as f64)
+            .round() as u32)
+            .max(1);
+        let resized =
+            image::imageops::resize(&source, THUMBNAIL_SIZE, height, FilterType::Lanczos3);
+
+        let mut canvas = RgbImage::from_pixel(THUMBNAIL_SIZE, THUMBNAIL_SIZE, Rgb([255, 255, 255]));
+        let offset_y = (i64::from(THUMBNAIL_SIZE) - i64::from(height)) / 2;
+        image::imageops::overlay(&mut canvas, &resized, 0, offset_y);
+
+        let writer = BufWriter::new(File::create(thumbnail_path)?);
+        JpegEncoder::new_with_quality(writer, THUMBNAIL_QUALITY).encode_image(&canvas)?;
+
+        debug!(
+            "Successfully generated thumbnail: {}",
+            thumbnail_path.display()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 100x10 image split into vertical stripes: 50% red, 30% blue, and 20% green.
+    fn striped_image() -> RgbImage {
+        RgbImage::from_fn(100, 10, |x, _| match x {
+            0..50 => Rgb([255, 0, 0]),
+            50..80 => Rgb([0, 0, 255]),
+            _ => Rgb([0, 255, 0]),
+        })
+    }
+
+    fn backend_with(image: &RgbImage) -> NativeBackend {
+        let mut backend = NativeBackend::new();
+        backend.load_pixels(image).unwrap();
+        backend
+    }
+
+    #[test]
+    fn kmeans_finds_dominant_colors_by_pixel_count() {
+        let backend = backend_with(&striped_image());
+        let colors = backend.extract_kmeans_colors(3, 70).unwrap();
+        assert_eq!(
+            colors,
+            vec![
+                (500, "FF0000".to_string()),
+                (300, "0000FF".to_string()),
+                (200, "00FF00".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn kmeans_merges_clusters_beyond_the_distinct_colors() {
+        let backend = backend_with(&striped_image());
+        let colors = backend.extract_kmeans_colors(5, 70).unwrap();
+        assert_eq!(colors.len(), 3);
+        assert_eq!(colors.iter().map(|(count, _)| count).sum::<u64>(), 1000);
+    }
+
+    #[test]
+    fn whole_image_statistics() {
+        let black = backend_with(&RgbImage::from_pixel(4, 4, Rgb([0, 0, 0])));
+        assert!(black.is_image_dark().unwrap());
+        assert_eq!(black.average_saturation().unwrap(), 0.0);
+
+        let striped = backend_with(&striped_image());
+        assert!(striped.is_image_dark().unwrap());
+        assert!((striped.average_saturation().unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn queries_fail_before_an_image_is_loaded() {
+        assert!(NativeBackend::new().extract_kmeans_colors(4, 70).is_err());
+    }
+}
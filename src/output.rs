@@ -1,23 +1,149 @@
 //! Handles writing palette data to output files (CSS, JSON, dcol) and applies user templates for config generation.
 //!
-//! This module generates standard output files and renders user-defined Tera templates, supporting output path expansion and backup logic for seamless ricing automation.
+//! This module generates standard output files and renders user-defined Tera templates, supporting output path expansion, backup logic, and per-template reload hooks for seamless ricing automation. All files of a run are written atomically and rolled back together if any of them fails.
+use crate::atomic::Transaction;
+use crate::base16;
 use crate::config::{AppPaths, Palette, ACCENT_COUNT};
 use crate::error::WallbashError;
+use crate::filters;
+use crate::pywal;
+use crate::swatches::SwatchFormat;
+use crate::writers::Emitter;
+use clap::ValueEnum;
+use log::{info, warn};
 use std::fs::{self, File};
-use std::io::Write;
+use std::io::{BufRead, Write};
 use std::path::Path;
+use std::time::Duration;
 use tera::{Context, Tera};
-use std::io::BufRead;
-use shellexpand;
+
+/// Options controlling which outputs are written and how.
+#[derive(Debug, Clone)]
+pub struct OutputOptions {
+    /// Skip rendering user templates (and therefore their hooks).
+    pub skip_templates: bool,
+    /// Maximum run time of each template hook.
+    pub hook_timeout: Duration,
+    /// Report what would change instead of writing anything or running hooks.
+    pub dry_run: bool,
+    /// Also print the palette to stdout in this format.
+    pub print: Option<PrintFormat>,
+    /// Write no output files, templates, or HTML preview, and run no hooks.
+    pub no_write: bool,
+    /// Send the palette to open terminals and save the escape sequences.
+    pub apply_terminals: bool,
+    /// Built-in app writers to run.
+    pub emit: Vec<Emitter>,
+    /// Also write pywal's color files to `~/.cache/wal`.
+    pub pywal_compat: bool,
+    /// Swatch files to write for design tools.
+    pub swatches: Vec<SwatchFormat>,
+}
+
+/// Formats the palette can be printed to stdout in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PrintFormat {
+    /// Pretty-printed JSON, as in `wallrust.json`.
+    Json,
+    /// Shell variables, as in `wallrust.dcol`.
+    Dcol,
+    /// CSS custom properties, as in `wallrust.css`.
+    Css,
+    /// `export WALLRUST_*=...` lines for `eval` in a shell.
+    Env,
+}
+
+/// Renders the palette in the given print format.
+pub fn format_palette(palette: &Palette, format: PrintFormat) -> Result<String, WallbashError> {
+    let bytes = match format {
+        PrintFormat::Json => {
+            let mut json = serde_json::to_string_pretty(palette)?;
+            json.push('\n');
+            return Ok(json);
+        }
+        PrintFormat::Dcol => dcol_contents(palette)?,
+        PrintFormat::Css => css_contents(palette)?,
+        PrintFormat::Env => env_contents(palette)?,
+    };
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Renders the palette as exported shell variables, e.g. `WALLRUST_PRY1` and `WALLRUST_COLOR4`.
+fn env_contents(palette: &Palette) -> Result<Vec<u8>, WallbashError> {
+    let mut writer = Vec::new();
+    // Single-quote values so paths with spaces or `$` survive `eval`.
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "'\\''"));
+
+    writeln!(writer, "export WALLRUST_MODE={}", quote(&palette.mode))?;
+    writeln!(
+        writer,
+        "export WALLRUST_WALLPAPER={}",
+        quote(&palette.wallpaper)
+    )?;
+    for (i, pry) in palette.primary.iter().enumerate() {
+        writeln!(writer, "export WALLRUST_PRY{}={}", i + 1, quote(pry))?;
+        if let Some(txt) = palette.text.get(i) {
+            writeln!(writer, "export WALLRUST_TXT{}={}", i + 1, quote(txt))?;
+        }
+        for (j, accent) in palette.accents.get(i).into_iter().flatten().enumerate() {
+            writeln!(
+                writer,
+                "export WALLRUST_{}XA{}={}",
+                i + 1,
+                j + 1,
+                quote(accent)
+            )?;
+        }
+    }
+    if !palette.ansi.is_empty() {
+        for (i, ansi_color) in palette.ansi.colors().iter().enumerate() {
+            writeln!(writer, "export WALLRUST_COLOR{}={}", i, quote(ansi_color))?;
+        }
+        writeln!(
+            writer,
+            "export WALLRUST_FOREGROUND={}",
+            quote(&palette.ansi.foreground)
+        )?;
+        writeln!(
+            writer,
+            "export WALLRUST_BACKGROUND={}",
+            quote(&palette.ansi.background)
+        )?;
+        writeln!(
+            writer,
+            "export WALLRUST_CURSOR={}",
+            quote(&palette.ansi.cursor)
+        )?;
+        writeln!(
+            writer,
+            "export WALLRUST_SELECTION={}",
+            quote(&palette.ansi.selection)
+        )?;
+    }
+    Ok(writer)
+}
 
 /// Writes the palette to a dcol file (shell variable format for theme scripts).
-pub fn write_dcol(palette: &Palette, dcol_path: &Path) -> Result<(), WallbashError> {
-    
-    if let Some(parent) = dcol_path.parent() {
-        fs::create_dir_all(parent)?;
+pub fn write_dcol(
+    palette: &Palette,
+    dcol_path: &Path,
+    transaction: &mut Transaction,
+) -> Result<(), WallbashError> {
+    transaction.write(dcol_path, &dcol_contents(palette)?)?;
+    report_generated(transaction, dcol_path);
+    Ok(())
+}
+
+/// Logs a written file, unless the transaction only reports changes.
+fn report_generated(transaction: &Transaction, path: &Path) {
+    if !transaction.is_dry_run() {
+        info!("Generated {}", path.display());
     }
-    
-    let mut writer = File::create(dcol_path)?;
+}
+
+/// Renders the palette in dcol format.
+fn dcol_contents(palette: &Palette) -> Result<Vec<u8>, WallbashError> {
+    let mut writer = Vec::new();
 
     writeln!(writer, "dcol_mode=\"{}\"", palette.mode)?;
     writeln!(writer, "dcol_wallpaper=\"{}\"", palette.wallpaper)?;
@@ -49,13 +175,34 @@ pub fn write_dcol(palette: &Palette, dcol_path: &Path) -> Result<(), WallbashErr
         writeln!(writer)?;
     }
 
-    println!("Generated {}", dcol_path.display());
-    Ok(())
+    if !palette.ansi.is_empty() {
+        for (i, ansi_color) in palette.ansi.colors().iter().enumerate() {
+            writeln!(writer, "dcol_ansi{}=\"{}\"", i, ansi_color)?;
+        }
+        writeln!(writer, "dcol_ansi_fg=\"{}\"", palette.ansi.foreground)?;
+        writeln!(writer, "dcol_ansi_bg=\"{}\"", palette.ansi.background)?;
+        writeln!(writer, "dcol_ansi_cursor=\"{}\"", palette.ansi.cursor)?;
+        writeln!(writer, "dcol_ansi_selection=\"{}\"", palette.ansi.selection)?;
+    }
+
+    Ok(writer)
 }
 
-fn write_css(palette: &Palette, paths: &AppPaths) -> Result<(), WallbashError> {
+fn write_css(
+    palette: &Palette,
+    paths: &AppPaths,
+    transaction: &mut Transaction,
+) -> Result<(), WallbashError> {
     let css_path = paths.output_dir.join("wallrust.css");
-    let mut writer = File::create(&css_path)?;
+    transaction.write(&css_path, &css_contents(palette)?)?;
+    report_generated(transaction, &css_path);
+    Ok(())
+}
+
+/// Renders the palette as CSS custom properties.
+#[allow(clippy::get_first)]
+fn css_contents(palette: &Palette) -> Result<Vec<u8>, WallbashError> {
+    let mut writer = Vec::new();
 
     writeln!(writer, "/* Wallbash Palette */")?;
     writeln!(writer, "/* Wallpaper: {} */", palette.wallpaper)?;
@@ -91,153 +238,422 @@ fn write_css(palette: &Palette, paths: &AppPaths) -> Result<(), WallbashError> {
 
     writeln!(writer, "}}")?;
 
-    println!("Generated {}", css_path.display());
-    Ok(())
+    Ok(writer)
 }
 
-fn write_json(palette: &Palette, paths: &AppPaths) -> Result<(), WallbashError> {
+/// Writes the palette as pretty-printed JSON to `wallrust.json` in the output directory.
+pub fn write_json(
+    palette: &Palette,
+    paths: &AppPaths,
+    transaction: &mut Transaction,
+) -> Result<(), WallbashError> {
     let json_path = paths.output_dir.join("wallrust.json");
     let json_string = serde_json::to_string_pretty(palette)?;
-    let mut file = File::create(&json_path)?;
-    file.write_all(json_string.as_bytes())?;
+    transaction.write(&json_path, json_string.as_bytes())?;
 
-    println!("Generated {}", json_path.display());
+    report_generated(transaction, &json_path);
     Ok(())
 }
 
-fn apply_templates(palette: &Palette, paths: &AppPaths) -> Result<(), WallbashError> {
+/// The Tera context for a palette: its fields plus the Base24 scheme keys `base00`-`base17`.
+fn template_context(palette: &Palette) -> Result<Context, WallbashError> {
+    let mut context = Context::from_serialize(palette)?;
+    if !palette.ansi.is_empty() {
+        for (i, hex) in base16::scheme(palette)?.into_iter().enumerate() {
+            context.insert(base16::key(i), &hex);
+        }
+    }
+    Ok(context)
+}
+
+/// Prefix of the names built-in writers are registered under, keeping them apart from user template paths.
+const BUILTIN_PREFIX: &str = "builtin:";
+
+fn builtin_name(emitter: Emitter) -> String {
+    format!("{}{}", BUILTIN_PREFIX, emitter.name())
+}
+
+/// A Tera instance holding only the built-in writers, with the color filters registered.
+fn builtin_tera() -> Tera {
+    let mut tera = Tera::default();
+    tera.autoescape_on(vec![]);
+    filters::register(&mut tera);
+    let writers = Emitter::value_variants()
+        .iter()
+        .map(|&emitter| (builtin_name(emitter), emitter.template()));
+    tera.add_raw_templates(writers)
+        .expect("built-in writers are valid templates");
+    tera
+}
+
+/// User templates and built-in writers in one Tera instance, built on first use and reused for the rest of the process.
+#[derive(Default)]
+pub struct Templates {
+    tera: Option<Tera>,
+    /// Whether `tera` was loaded from the template directory glob and can be reloaded in place.
+    from_glob: bool,
+}
+
+impl Templates {
+    /// Returns the shared instance and whether the user templates loaded, re-reading them so edits made while watching are picked up.
+    ///
+    /// If a user template fails to parse, only the built-in writers are rendered.
+    fn load(&mut self, template_dir: &Path) -> (&Tera, bool) {
+        let result = match self.tera.as_mut() {
+            Some(tera) if self.from_glob => tera.full_reload(),
+            _ => {
+                let builtins = self.tera.take().unwrap_or_else(builtin_tera);
+                match Tera::new(&format!("{}/**/*", template_dir.display())) {
+                    Ok(mut tera) => {
+                        tera.autoescape_on(vec![]);
+                        let result = tera.extend(&builtins);
+                        self.tera = Some(tera);
+                        self.from_glob = true;
+                        result
+                    }
+                    Err(e) => {
+                        self.tera = Some(builtins);
+                        Err(e)
+                    }
+                }
+            }
+        };
+        let user_templates = match result {
+            Ok(()) => true,
+            Err(e) => {
+                warn!(
+                    "Failed to initialize Tera templates from '{}': {}",
+                    template_dir.display(),
+                    e
+                );
+                false
+            }
+        };
+        (
+            self.tera.as_ref().expect("templates were just loaded"),
+            user_templates,
+        )
+    }
+}
+
+/// Renders every template into `transaction`, returning the hooks of the written templates in order.
+#[allow(clippy::manual_flatten)]
+fn apply_templates(
+    tera: &Tera,
+    context: &Context,
+    paths: &AppPaths,
+    transaction: &mut Transaction,
+) -> Result<Vec<String>, WallbashError> {
+    let mut pending_hooks = Vec::new();
     if !paths.template_dir.exists() {
-        println!(
+        info!(
             "Template directory not found, skipping custom templates: {}",
             paths.template_dir.display()
         );
-        return Ok(());
+        return Ok(pending_hooks);
     }
     if !paths.template_dir.is_dir() {
-        eprintln!(
-            "Warning: Template path is not a directory: {}",
+        warn!(
+            "Template path is not a directory: {}",
             paths.template_dir.display()
         );
-        return Ok(());
+        return Ok(pending_hooks);
     }
 
-    let mut tera = match Tera::new(&format!("{}/*", paths.template_dir.display())) {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!(
-                "Warning: Failed to initialize Tera templates from '{}': {}",
-                paths.template_dir.display(),
-                e
-            );
-            return Ok(());
-        }
-    };
-
-    tera.autoescape_on(vec![]);
 
-    let context = Context::from_serialize(palette)?;
-
-    for template_entry in fs::read_dir(&paths.template_dir)? {
-        let template_path = match template_entry {
-            Ok(entry) => entry.path(),
-            Err(e) => {
-                eprintln!("Warning: Failed to read entry in template directory: {}", e);
-                continue;
-            }
-        };
+    // Template names are paths relative to the template directory, e.g. `waybar/style.css`.
+    let mut template_names: Vec<String> = tera
+        .get_template_names()
+        .filter(|name| !name.starts_with(BUILTIN_PREFIX))
+        .map(String::from)
+        .collect();
+    template_names.sort();
 
-        if template_path.is_file() {
-            if let Some(template_name) = template_path.file_name().and_then(|n| n.to_str()) {
-                let mut output_path_override: Option<String> = None;
-                let mut backup_enabled: bool = false;
-                if let Ok(file) = File::open(&template_path) {
-                    let reader = std::io::BufReader::new(file);
-                    for line in reader.lines().take(5) { // Only check the first 5 lines
-                        if let Ok(l) = line {
-                            let trimmed = l.trim();
-                            if let Some(rest) = trimmed.strip_prefix("{# output:") {
-                                if let Some(path) = rest.strip_suffix("#}") {
-                                    output_path_override = Some(path.trim().to_string());
-                                }
-                            } else if let Some(rest) = trimmed.strip_prefix("#!output:") {
-                                output_path_override = Some(rest.trim().to_string());
-                            } else if let Some(rest) = trimmed.strip_prefix("{# backup:") {
-                                if let Some(val) = rest.strip_suffix("#}") {
-                                    backup_enabled = val.trim().eq_ignore_ascii_case("true");
-                                }
-                            } else if let Some(rest) = trimmed.strip_prefix("#!backup:") {
-                                backup_enabled = rest.trim().eq_ignore_ascii_case("true");
-                            }
+    for template_name in &template_names {
+        let template_name = template_name.as_str();
+        if is_partial(template_name) {
+            continue;
+        }
+        let template_path = paths.template_dir.join(template_name);
+        let mut output_path_override: Option<String> = None;
+        let mut backup_enabled: bool = false;
+        let mut hook_commands: Vec<String> = Vec::new();
+        if let Ok(file) = File::open(&template_path) {
+            let reader = std::io::BufReader::new(file);
+            for line in reader.lines().take(5) {
+                // Only check the first 5 lines
+                if let Ok(l) = line {
+                    let trimmed = l.trim();
+                    if let Some(rest) = trimmed.strip_prefix("{# output:") {
+                        if let Some(path) = rest.strip_suffix("#}") {
+                            output_path_override = Some(path.trim().to_string());
+                        }
+                    } else if let Some(rest) = trimmed.strip_prefix("#!output:") {
+                        output_path_override = Some(rest.trim().to_string());
+                    } else if let Some(rest) = trimmed.strip_prefix("{# backup:") {
+                        if let Some(val) = rest.strip_suffix("#}") {
+                            backup_enabled = val.trim().eq_ignore_ascii_case("true");
+                        }
+                    } else if let Some(rest) = trimmed.strip_prefix("#!backup:") {
+                        backup_enabled = rest.trim().eq_ignore_ascii_case("true");
+                    } else if let Some(rest) = trimmed.strip_prefix("{# hook:") {
+                        if let Some(command) = rest.strip_suffix("#}") {
+                            hook_commands.push(command.trim().to_string());
                         }
+                    } else if let Some(rest) = trimmed.strip_prefix("#!hook:") {
+                        hook_commands.push(rest.trim().to_string());
                     }
                 }
-                match tera.render(template_name, &context) {
-                    Ok(rendered_content) => {
-                        let output_path = if let Some(path) = output_path_override {
-                            
-                            match shellexpand::full(&path) {
-                                Ok(expanded) => Path::new(expanded.as_ref()).to_path_buf(),
-                                Err(_) => {
-                                    eprintln!("Warning: Failed to expand output path '{}', using default output dir.", path);
-                                    paths.output_dir.join(template_name)
-                                }
-                            }
-                        } else {
-                            paths.output_dir.join(template_name)
-                        };
-                        if let Some(parent) = output_path.parent() {
-                            if let Err(e) = fs::create_dir_all(parent) {
-                                eprintln!("Warning: Failed to create output directory '{}': {}", parent.display(), e);
-                            }
-                        }
-                        // Backup logic
-                        if backup_enabled && output_path.exists() {
-                            let backup_path = output_path.with_extension(
-                                match output_path.extension().and_then(|e| e.to_str()) {
-                                    Some(ext) => format!("{}.wr.bakup", ext),
-                                    None => "wr.bakup".to_string(),
-                                }
+            }
+        }
+        match tera.render(template_name, context) {
+            Ok(rendered_content) => {
+                let output_path = if let Some(path) = output_path_override {
+
+                    match shellexpand::full(&path) {
+                        Ok(expanded) => Path::new(expanded.as_ref()).to_path_buf(),
+                        Err(_) => {
+                            warn!(
+                                "Failed to expand output path '{}', using default output dir.",
+                                path
                             );
-                            if let Err(e) = fs::copy(&output_path, &backup_path) {
-                                eprintln!("Warning: Failed to backup '{}' to '{}': {}", output_path.display(), backup_path.display(), e);
-                            } else {
-                                println!("Backed up '{}' to '{}'", output_path.display(), backup_path.display());
-                            }
-                        }
-                        match fs::write(&output_path, rendered_content) {
-                            Ok(_) => println!("Generated from template: {}", output_path.display()),
-                            Err(e) => eprintln!(
-                                "Warning: Failed to write output file for template '{}': {}",
-                                template_name, e
-                            ),
+                            paths.output_dir.join(template_name)
                         }
                     }
-                    Err(e) => {
-                        eprintln!(
-                            "Warning: Failed to render template '{}': {}",
-                            template_name, e
-                        );
+                } else {
+                    paths.output_dir.join(template_name)
+                };
+                // Backup logic
+                if backup_enabled && output_path.exists() {
+                    let backup_path = output_path.with_extension(
+                        match output_path.extension().and_then(|e| e.to_str()) {
+                            Some(ext) => format!("{}.wr.bakup", ext),
+                            None => "wr.bakup".to_string(),
+                        }
+                    );
+                    match fs::read(&output_path)
+                        .map_err(WallbashError::from)
+                        .and_then(|previous| transaction.write(&backup_path, &previous))
+                    {
+                        Ok(()) if transaction.is_dry_run() => {}
+                        Ok(()) => info!(
+                            "Backed up '{}' to '{}'",
+                            output_path.display(),
+                            backup_path.display()
+                        ),
+                        Err(e) => warn!(
+                            "Failed to backup '{}' to '{}': {}",
+                            output_path.display(),
+                            backup_path.display(),
+                            e
+                        ),
                     }
                 }
+                if let Err(e) = transaction.write(&output_path, rendered_content.as_bytes()) {
+                    warn!(
+                        "Failed to write output file for template '{}': {}",
+                        template_name, e
+                    );
+                    return Err(e);
+                }
+                if !transaction.is_dry_run() {
+                    info!("Generated from template: {}", output_path.display());
+                }
+                pending_hooks.extend(hook_commands);
+            }
+            Err(e) => {
+                warn!("Failed to render template '{}': {}", template_name, e);
             }
         }
     }
 
+    Ok(pending_hooks)
+}
+
+/// Renders the selected built-in writers into the output directory, skipping those overridden by a user template
+/// that will be rendered in their place.
+fn emit_builtins(
+    tera: &Tera,
+    context: &Context,
+    paths: &AppPaths,
+    emit: &[Emitter],
+    user_templates: bool,
+    transaction: &mut Transaction,
+) -> Result<(), WallbashError> {
+    let user_names: Vec<&str> = if user_templates {
+        tera.get_template_names()
+            .filter(|name| !name.starts_with(BUILTIN_PREFIX))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    for &emitter in emit {
+        if let Some(template) = emitter.user_override(user_names.iter().copied()) {
+            info!(
+                "Using template '{}' instead of the built-in {} writer",
+                template,
+                emitter.name()
+            );
+            continue;
+        }
+        // Tera puts the useful part, e.g. a missing `accents[0][4]`, in the error's source.
+        let rendered = tera.render(&builtin_name(emitter), context).map_err(|e| {
+            let reason =
+                std::error::Error::source(&e).map_or_else(|| e.to_string(), ToString::to_string);
+            WallbashError::InvalidInput(format!(
+                "Built-in {} writer failed to render: {} (it needs {} accents per primary)",
+                emitter.name(),
+                reason,
+                ACCENT_COUNT
+            ))
+        })?;
+        let output_path = paths.output_dir.join(emitter.file_name());
+        transaction.write(&output_path, rendered.as_bytes())?;
+        report_generated(transaction, &output_path);
+    }
     Ok(())
 }
 
-/// Generates all standard output files (dcol, CSS, JSON) and applies user templates.
+/// Returns true for `_`-prefixed templates, which are only used via `include`/`extends`.
+fn is_partial(template_name: &str) -> bool {
+    Path::new(template_name)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with('_'))
+}
+
+/// Generates all standard output files (dcol, CSS, JSON) and applies user templates into `transaction`.
 ///
-/// If `skip_templates` is true, skips template rendering.
-pub fn generate_outputs(palette: &Palette, paths: &AppPaths, skip_templates: bool) -> Result<(), WallbashError> {
-    let dcol_path = paths.output_dir.join("wallrust.dcol");
-    write_dcol(palette, &dcol_path)?;
-    write_css(palette, paths)?;
-    write_json(palette, paths)?;
-    if !skip_templates {
-        apply_templates(palette, paths)?;
+/// If `options.skip_templates` is true, skips template rendering. Returns the hooks of the written templates,
+/// which the caller runs once every file of the run is in place.
+pub fn generate_outputs(
+    palette: &Palette,
+    paths: &AppPaths,
+    options: &OutputOptions,
+    templates: &mut Templates,
+    transaction: &mut Transaction,
+) -> Result<Vec<String>, WallbashError> {
+    write_dcol(
+        palette,
+        &paths.output_dir.join("wallrust.dcol"),
+        transaction,
+    )?;
+    write_css(palette, paths, transaction)?;
+    write_json(palette, paths, transaction)?;
+    let context = template_context(palette)?;
+    let (tera, user_templates) = templates.load(&paths.template_dir);
+    let user_templates = user_templates && !options.skip_templates;
+    emit_builtins(
+        tera,
+        &context,
+        paths,
+        &options.emit,
+        user_templates,
+        transaction,
+    )?;
+    for format in &options.swatches {
+        let path = paths.output_dir.join(format.file_name());
+        transaction.write(&path, &format.contents(palette)?)?;
+        report_generated(transaction, &path);
+    }
+    if options.pywal_compat {
+        pywal::write_files(palette, paths, transaction)?;
+    }
+    if options.skip_templates {
+        info!("Skipping custom template generation (--no-templates set)");
+        Ok(Vec::new())
+    } else if user_templates {
+        apply_templates(tera, &context, paths, transaction)
     } else {
-        println!("Skipping custom template generation (--no-templates set)");
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn env_output_round_trips_through_sh() {
+        let mut palette = Palette {
+            mode: "dark".to_string(),
+            wallpaper: "/walls/it's a \"wall\" $HOME `id` \\ *.png".to_string(),
+            primary: vec!["1B2A24".to_string()],
+            text: vec!["E0EEE5".to_string()],
+            accents: vec![vec!["2E4A3E".to_string(), "4F7A67".to_string()]],
+            ..Palette::default()
+        };
+        for (i, value) in palette.ansi.values_mut().into_iter().enumerate() {
+            *value = format!("{:06X}", i);
+        }
+
+        let names = [
+            "WALLRUST_MODE",
+            "WALLRUST_WALLPAPER",
+            "WALLRUST_PRY1",
+            "WALLRUST_TXT1",
+            "WALLRUST_1XA2",
+            "WALLRUST_COLOR15",
+            "WALLRUST_SELECTION",
+        ];
+        let script = format!(
+            "{}printf '%s\\0' {}",
+            format_palette(&palette, PrintFormat::Env).unwrap(),
+            names.map(|name| format!("\"${}\"", name)).join(" ")
+        );
+        let output = Command::new("sh").arg("-c").arg(script).output().unwrap();
+        assert!(output.status.success());
+        let values: Vec<&str> = std::str::from_utf8(&output.stdout)
+            .unwrap()
+            .split_terminator('\0')
+            .collect();
+        assert_eq!(
+            values,
+            [
+                "dark",
+                palette.wallpaper.as_str(),
+                "1B2A24",
+                "E0EEE5",
+                "4F7A67",
+                "00000F",
+                "000013",
+            ]
+        );
+    }
+
+    #[test]
+    fn base16_and_base24_writers_round_trip_through_parse_scheme() {
+        let mut palette = Palette {
+            mode: "dark".to_string(),
+            is_dark: true,
+            wallpaper: "/walls/forest.png".to_string(),
+            ..Palette::default()
+        };
+        let ansi = [
+            "1B2A24", "D0645A", "6DB37A", "D9B45C", "5C8FD6", "B07AD0", "55B5B0", "C8D6CE",
+            "4A5C53", "E57D73", "85CC92", "EBC875", "76A6E8", "C693E2", "6ECBC6", "E8F2EC",
+            "E0EEE5", "1B2A24", "E0EEE5", "35503D",
+        ];
+        for (value, hex) in palette.ansi.values_mut().into_iter().zip(ansi) {
+            *value = hex.to_string();
+        }
+        let context = template_context(&palette).unwrap();
+        let tera = builtin_tera();
+
+        for (emitter, len) in [
+            (Emitter::Base16, base16::BASE16_LEN),
+            (Emitter::Base24, base16::BASE24_LEN),
+        ] {
+            let yaml = tera.render(&builtin_name(emitter), &context).unwrap();
+            let parsed = base16::parse_scheme(&yaml).unwrap();
+            assert_eq!(parsed.mode, palette.mode);
+            assert_eq!(
+                base16::scheme(&parsed).unwrap()[..len],
+                base16::scheme(&palette).unwrap()[..len],
+                "{} scheme changed in the round trip",
+                emitter.name()
+            );
+        }
     }
-    Ok(())
 }
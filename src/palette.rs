@@ -1,14 +1,18 @@
-//! Generates the full color palette (primary, text, accents, RGBA) from extracted colors and user profile.
+//! Generates the full color palette (primary, text, accents, RGBA, ANSI) from extracted colors and user profile.
 //!
 //! This module contains the core logic for palette construction, including color sorting, dark/light mode detection, accent generation, and support for custom color curves.
+use crate::ansi;
+use crate::backend::ColorBackend;
+use crate::base16;
+use crate::color;
 use crate::config::{
-    ColorProfile, Palette, SortMode, ACCENT_COUNT, CURVE_GRAYSCALE, PRY_DARK_BRI, PRY_DARK_HUE,
-    PRY_DARK_SAT, PRY_LIGHT_BRI, PRY_LIGHT_HUE, PRY_LIGHT_SAT, TXT_DARK_BRI, TXT_LIGHT_BRI,
+    ACCENT_COUNT, AnsiPalette, CURVE_GRAYSCALE, ColorSpace, ExtractionSettings, OKLCH_MAX_CHROMA,
+    Palette, SortMode, TXT_OKLCH_CHROMA_SCALE, TXT_OKLCH_DARK_L, TXT_OKLCH_LIGHT_L,
 };
 use crate::error::WallbashError;
-use crate::imagemagick::{
-    check_brightness_dark, color_from_hsb, get_average_saturation, get_hsb_hue, modulate_color,
-};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::fs;
 use std::path::Path;
 
 /// Returns the RGB negative (inverted color) of a hex color string.
@@ -56,27 +60,31 @@ fn calculate_luma(hex_color: &str) -> Result<f64, WallbashError> {
         return Ok(0.0);
     }
 
-    let r_srgb = u8::from_str_radix(&hex[0..2], 16)? as f64 / 255.0;
-    let g_srgb = u8::from_str_radix(&hex[2..4], 16)? as f64 / 255.0;
-    let b_srgb = u8::from_str_radix(&hex[4..6], 16)? as f64 / 255.0;
+    Ok(color::relative_luminance(color::parse_hex(hex)?))
+}
 
-    let r = if r_srgb <= 0.03928 {
-        r_srgb / 12.92
+/// Derives a text color for a primary in OKLCH: opposite hue, muted chroma, and a fixed lightness on the far side of the primary.
+fn oklch_text_color(primary_hex: &str, is_pry_dark: bool) -> Result<String, WallbashError> {
+    let (_, chroma, hue) = color::rgb_to_oklch(color::parse_hex(primary_hex)?);
+    let lightness = if is_pry_dark {
+        TXT_OKLCH_DARK_L
     } else {
-        ((r_srgb + 0.055) / 1.055).powf(2.4)
-    };
-    let g = if g_srgb <= 0.03928 {
-        g_srgb / 12.92
-    } else {
-        ((g_srgb + 0.055) / 1.055).powf(2.4)
-    };
-    let b = if b_srgb <= 0.03928 {
-        b_srgb / 12.92
-    } else {
-        ((b_srgb + 0.055) / 1.055).powf(2.4)
+        TXT_OKLCH_LIGHT_L
     };
+    Ok(color::to_hex(color::oklch_to_rgb(
+        lightness,
+        chroma * TXT_OKLCH_CHROMA_SCALE,
+        (hue + 180.0) % 360.0,
+    )))
+}
 
-    Ok(0.2126 * r + 0.7152 * g + 0.0722 * b)
+/// Builds an accent from a curve point in OKLCH, mapping brightness to lightness and saturation to a fraction of `OKLCH_MAX_CHROMA`.
+fn oklch_accent_color(hue: f64, sat: u8, bri: u8) -> String {
+    color::to_hex(color::oklch_to_rgb(
+        f64::from(bri) / 100.0,
+        f64::from(sat) / 100.0 * OKLCH_MAX_CHROMA,
+        hue,
+    ))
 }
 
 /// Parses a color curve string into a vector of (brightness, saturation) points.
@@ -114,10 +122,11 @@ fn parse_curve(curve_str: &str) -> Result<Vec<(u8, u8)>, WallbashError> {
         }
     }
     if points.len() != ACCENT_COUNT {
-        eprintln!(
-             "Warning: Parsed curve has {} points, but {} are expected for standard accent generation.",
-             points.len(), ACCENT_COUNT
-         );
+        warn!(
+            "Parsed curve has {} points, but {} are expected for standard accent generation.",
+            points.len(),
+            ACCENT_COUNT
+        );
     }
     Ok(points)
 }
@@ -125,27 +134,32 @@ fn parse_curve(curve_str: &str) -> Result<Vec<(u8, u8)>, WallbashError> {
 /// Generates a full color palette (primary, text, accents, RGBA) from extracted colors and user profile.
 ///
 /// This is the main entry point for palette construction, handling color sorting, mode detection, and accent generation.
+/// Whole-image queries run against the image already loaded into `backend`; per-color math is done in-process.
 pub fn generate_palette(
     wallpaper_path: &Path,
-    mpc_path: &Path,
+    backend: &dyn ColorBackend,
     mut initial_hex_colors: Vec<String>,
     num_colors: usize,
-    profile: &ColorProfile,
-    initial_sort_mode: SortMode,
+    settings: &ExtractionSettings,
 ) -> Result<Palette, WallbashError> {
-    let mut palette = Palette::default();
-    palette.wallpaper = wallpaper_path.display().to_string();
+    let initial_sort_mode = settings.sort_mode;
+    let colorspace = settings.colorspace;
+    let min_contrast = settings.min_contrast;
+    let modulation = settings.color_profile.modulation();
+    let mut palette = Palette {
+        wallpaper: wallpaper_path.display().to_string(),
+        ..Default::default()
+    };
 
     let mut final_sort_mode = initial_sort_mode;
     if initial_sort_mode == SortMode::Auto {
-        let mpc_target = format!("mpc:{}", mpc_path.to_str().unwrap());
-        let is_dark = check_brightness_dark(&mpc_target)?;
+        let is_dark = backend.is_image_dark()?;
         final_sort_mode = if is_dark {
             SortMode::Dark
         } else {
             SortMode::Light
         };
-        println!("Auto-detected sort mode: {}", final_sort_mode);
+        info!("Auto-detected sort mode: {}", final_sort_mode);
     }
     palette.mode = final_sort_mode.to_string();
     palette.is_dark = final_sort_mode == SortMode::Dark;
@@ -153,17 +167,21 @@ pub fn generate_palette(
     
     
     initial_hex_colors.sort_by_cached_key(|hex| {
-        ordered_float::NotNan::new(calculate_luma(hex).unwrap_or(0.0)).unwrap_or_default()
+        let lightness = match colorspace {
+            ColorSpace::Hsb => calculate_luma(hex),
+            ColorSpace::Oklch => color::oklab_lightness(hex),
+        };
+        ordered_float::NotNan::new(lightness.unwrap_or(0.0)).unwrap_or_default()
     });
     if final_sort_mode == SortMode::Light {
         initial_hex_colors.reverse();
     }
     
 
-    let mut current_curve_str = profile.to_curve_string();
-    let saturation = get_average_saturation(mpc_path)?;
+    let mut current_curve_str = settings.color_profile.to_curve_string();
+    let saturation = backend.average_saturation()?;
     if saturation < 0.12 {
-        println!("Image detected as low saturation/grayscale, using mono curve.");
+        info!("Image detected as low saturation/grayscale, using mono curve.");
         current_curve_str = CURVE_GRAYSCALE.to_string();
     }
 
@@ -174,6 +192,7 @@ pub fn generate_palette(
     palette.accents = vec![Vec::with_capacity(ACCENT_COUNT); num_colors];
     palette.primary_rgba = vec![String::new(); num_colors];
     palette.text_rgba = vec![String::new(); num_colors];
+    palette.text_contrast = vec![0.0; num_colors];
     palette.accents_rgba = vec![Vec::with_capacity(ACCENT_COUNT); num_colors];
 
     for i in 0..num_colors {
@@ -181,21 +200,28 @@ pub fn generate_palette(
             hex
         } else if i > 0 && !palette.primary[i - 1].is_empty() {
             let prev_hex = &palette.primary[i - 1];
-            println!(
+            info!(
                 "Regenerating missing primary color {} from {}",
                 i + 1,
                 prev_hex
             );
 
-            let prev_target = format!("xc:#{}", prev_hex);
-            let is_prev_dark = check_brightness_dark(&prev_target)?;
+            let is_prev_dark = color::is_dark(prev_hex)?;
             let (mod_bri, mod_sat, mod_hue) = if is_prev_dark {
-                (PRY_DARK_BRI, PRY_DARK_SAT, PRY_DARK_HUE)
+                (
+                    modulation.pry_dark_bri,
+                    modulation.pry_dark_sat,
+                    modulation.pry_dark_hue,
+                )
             } else {
-                (PRY_LIGHT_BRI, PRY_LIGHT_SAT, PRY_LIGHT_HUE)
+                (
+                    modulation.pry_light_bri,
+                    modulation.pry_light_sat,
+                    modulation.pry_light_hue,
+                )
             };
 
-            modulate_color(&prev_target, mod_bri, mod_sat, mod_hue)?
+            color::modulate(prev_hex, mod_bri, mod_sat, mod_hue)?
         } else {
             return Err(WallbashError::NotEnoughColors {
                 required: num_colors,
@@ -206,30 +232,45 @@ pub fn generate_palette(
         palette.primary[i] = current_hex.clone();
         palette.primary_rgba[i] = rgba_convert(&current_hex)?;
 
-        let n_txt = rgb_negative(&current_hex)?;
-        let pry_target = format!("xc:#{}", current_hex);
-        let is_pry_dark = check_brightness_dark(&pry_target)?;
-        let mod_bri_txt = if is_pry_dark {
-            TXT_DARK_BRI
+        let is_pry_dark = color::is_dark(&current_hex)?;
+        let tcol = match colorspace {
+            ColorSpace::Hsb => {
+                let n_txt = rgb_negative(&current_hex)?;
+                let mod_bri_txt = if is_pry_dark {
+                    modulation.txt_dark_bri
+                } else {
+                    modulation.txt_light_bri
+                };
+                color::modulate(&n_txt, mod_bri_txt, 10, 100)?
+            }
+            ColorSpace::Oklch => oklch_text_color(&current_hex, is_pry_dark)?,
+        };
+        let (tcol, contrast) = if min_contrast > 0.0 {
+            color::enforce_contrast(&tcol, &current_hex, min_contrast)?
         } else {
-            TXT_LIGHT_BRI
+            let ratio = color::contrast_ratio(&tcol, &current_hex)?;
+            (tcol, ratio)
         };
-
-        let tcol = modulate_color(&format!("xc:#{}", n_txt), mod_bri_txt, 10, 100)?;
+        palette.text_contrast[i] = (contrast * 100.0).round() / 100.0;
         palette.text[i] = tcol.clone();
         palette.text_rgba[i] = rgba_convert(&tcol)?;
 
-        let x_hue = get_hsb_hue(&pry_target)?;
+        let x_hue = match colorspace {
+            ColorSpace::Hsb => color::hsb_hue(&current_hex)?,
+            ColorSpace::Oklch => color::rgb_to_oklch(color::parse_hex(&current_hex)?).2,
+        };
 
         let mut sorted_curve = curve_points.clone();
-        sorted_curve.sort_by(|a, b| a.0.cmp(&b.0));
+        sorted_curve.sort_by_key(|&(bri, _)| bri);
         if final_sort_mode == SortMode::Light {
             sorted_curve.reverse();
         }
 
         for (x_bri, x_sat) in sorted_curve.iter().take(ACCENT_COUNT) {
-            let hsb_arg = format!("hsb({},{}%,{}%)", x_hue, x_sat, x_bri);
-            let acol = color_from_hsb(&hsb_arg)?;
+            let acol = match colorspace {
+                ColorSpace::Hsb => color::color_from_hsb(x_hue, *x_sat, *x_bri),
+                ColorSpace::Oklch => oklch_accent_color(x_hue, *x_sat, *x_bri),
+            };
             palette.accents[i].push(acol.clone());
             palette.accents_rgba[i].push(rgba_convert(&acol)?);
         }
@@ -237,5 +278,239 @@ pub fn generate_palette(
         palette.accents_rgba[i].resize(ACCENT_COUNT, rgba_convert("000000")?);
     }
 
+    palette.ansi = ansi::generate_ansi(&palette)?;
+
     Ok(palette)
 }
+
+fn read_palette_file(path: &Path) -> Result<String, WallbashError> {
+    fs::read_to_string(path).map_err(|e| {
+        WallbashError::Io(std::io::Error::new(
+            e.kind(),
+            format!("Failed to read palette '{}': {}", path.display(), e),
+        ))
+    })
+}
+
+fn normalize_hex(hex: &str) -> String {
+    hex.trim().trim_start_matches('#').to_uppercase()
+}
+
+/// Recomputes everything derivable from the hex colors of a loaded palette, so hand-edited files stay consistent.
+///
+/// The RGBA strings, text contrast, and `is_dark` are always rebuilt. The ANSI colors are kept only when complete and still built on the first primary and text color, which they use as background and foreground.
+fn complete_loaded_palette(mut palette: Palette) -> Result<Palette, WallbashError> {
+    if palette.primary.is_empty() || palette.text.len() != palette.primary.len() {
+        return Err(WallbashError::InvalidInput(
+            "Palette needs at least one primary color and one text color per primary".to_string(),
+        ));
+    }
+
+    for hex in palette
+        .primary
+        .iter_mut()
+        .chain(palette.text.iter_mut())
+        .chain(palette.accents.iter_mut().flatten())
+    {
+        *hex = normalize_hex(hex);
+        color::parse_hex(hex)?;
+    }
+
+    palette.is_dark = palette.mode == "dark";
+    palette.primary_rgba = palette
+        .primary
+        .iter()
+        .map(|hex| rgba_convert(hex))
+        .collect::<Result<_, _>>()?;
+    palette.text_rgba = palette
+        .text
+        .iter()
+        .map(|hex| rgba_convert(hex))
+        .collect::<Result<_, _>>()?;
+    palette.accents_rgba = palette
+        .accents
+        .iter()
+        .map(|accents| accents.iter().map(|hex| rgba_convert(hex)).collect())
+        .collect::<Result<_, _>>()?;
+    palette.text_contrast = palette
+        .text
+        .iter()
+        .zip(&palette.primary)
+        .map(|(txt, pry)| {
+            color::contrast_ratio(txt, pry).map(|ratio| (ratio * 100.0).round() / 100.0)
+        })
+        .collect::<Result<_, _>>()?;
+
+    let ansi = &mut palette.ansi;
+    let incomplete = ansi.values_mut().into_iter().any(|hex| {
+        *hex = normalize_hex(hex);
+        hex.is_empty()
+    });
+    if incomplete {
+        palette.ansi = ansi::generate_ansi(&palette)?;
+    } else if palette.ansi.background != palette.primary[0]
+        || palette.ansi.foreground != palette.text[0]
+    {
+        info!(
+            "Regenerating terminal colors: the first primary or text color changed since they were saved."
+        );
+        palette.ansi = ansi::generate_ansi(&palette)?;
+    } else {
+        for hex in palette.ansi.values_mut() {
+            color::parse_hex(hex)?;
+        }
+    }
+    Ok(palette)
+}
+
+/// Loads a palette from a JSON file in the format written to `wallrust.json`.
+pub fn load_palette(path: &Path) -> Result<Palette, WallbashError> {
+    let content = read_palette_file(path)?;
+    complete_loaded_palette(serde_json::from_str(&content)?)
+}
+
+/// Loads a palette from a dcol file in the format written by `write_dcol`.
+///
+/// Only the hex values are read; RGBA strings are rebuilt, and the ANSI colors are generated if the file has none or they are stale.
+pub fn load_palette_dcol(path: &Path) -> Result<Palette, WallbashError> {
+    let content = read_palette_file(path)?;
+    let mut values = HashMap::new();
+    for line in content.lines() {
+        if let Some((key, value)) = line.trim().split_once('=')
+            && let Some(key) = key.strip_prefix("dcol_")
+        {
+            values.insert(key.to_string(), value.trim().trim_matches('"').to_string());
+        }
+    }
+
+    let missing = |key: &str| {
+        WallbashError::InvalidInput(format!(
+            "dcol file '{}' is missing dcol_{}",
+            path.display(),
+            key
+        ))
+    };
+    let mut get = |key: &str| values.remove(key).ok_or_else(|| missing(key));
+
+    let mut palette = Palette {
+        mode: get("mode")?,
+        wallpaper: get("wallpaper").unwrap_or_default(),
+        ..Default::default()
+    };
+    for i in 1.. {
+        let Ok(pry) = get(&format!("pry{}", i)) else {
+            break;
+        };
+        palette.primary.push(pry);
+        palette.text.push(get(&format!("txt{}", i))?);
+        let accents = (1..=ACCENT_COUNT)
+            .map_while(|j| get(&format!("{}xa{}", i, j)).ok())
+            .collect();
+        palette.accents.push(accents);
+    }
+
+    let ansi_keys: Vec<String> = (0..16)
+        .map(|i| format!("ansi{}", i))
+        .chain(["ansi_fg", "ansi_bg", "ansi_cursor", "ansi_selection"].map(String::from))
+        .collect();
+    if let Ok(ansi) = ansi_keys
+        .iter()
+        .map(|key| get(key))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        let mut ansi = ansi.into_iter().map(|hex| normalize_hex(&hex));
+        let mut next = || ansi.next().unwrap_or_default();
+        palette.ansi = AnsiPalette {
+            color0: next(),
+            color1: next(),
+            color2: next(),
+            color3: next(),
+            color4: next(),
+            color5: next(),
+            color6: next(),
+            color7: next(),
+            color8: next(),
+            color9: next(),
+            color10: next(),
+            color11: next(),
+            color12: next(),
+            color13: next(),
+            color14: next(),
+            color15: next(),
+            foreground: next(),
+            background: next(),
+            cursor: next(),
+            selection: next(),
+        };
+    }
+
+    complete_loaded_palette(palette)
+}
+
+/// Loads a palette from a Base16 or Base24 YAML scheme, as consumed by tinted-theming builders.
+pub fn load_palette_base16(path: &Path) -> Result<Palette, WallbashError> {
+    let content = read_palette_file(path)?;
+    let mut palette = base16::parse_scheme(&content)?;
+    palette.wallpaper = path.display().to_string();
+    complete_loaded_palette(palette)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oklch_accents_keep_curve_lightness_inside_gamut() {
+        let curve = parse_curve(crate::config::CURVE_VIBRANT).unwrap();
+        for hue in (0..360).step_by(15) {
+            for &(bri, sat) in &curve {
+                let accent = oklch_accent_color(f64::from(hue), sat, bri);
+                let (lightness, _, _) = color::rgb_to_oklch(color::parse_hex(&accent).unwrap());
+                assert!(
+                    (lightness - f64::from(bri) / 100.0).abs() < 0.01,
+                    "#{} for hue {} should have lightness {}",
+                    accent,
+                    hue,
+                    bri
+                );
+            }
+        }
+    }
+
+    fn loaded_palette() -> Palette {
+        let palette = Palette {
+            mode: "dark".to_string(),
+            primary: vec!["050910".to_string(), "305B89".to_string()],
+            text: vec!["FFFFFF".to_string(), "FFFFFF".to_string()],
+            accents: vec![vec!["293852".to_string()], vec!["7A94C2".to_string()]],
+            ..Default::default()
+        };
+        complete_loaded_palette(palette).unwrap()
+    }
+
+    #[test]
+    fn loaded_ansi_is_kept_and_normalized() {
+        let mut palette = loaded_palette();
+        palette.ansi.color1 = "#ff0000".to_string();
+        let palette = complete_loaded_palette(palette).unwrap();
+        assert_eq!(palette.ansi.color1, "FF0000");
+    }
+
+    #[test]
+    fn loaded_ansi_is_regenerated_for_edited_primaries() {
+        let mut palette = loaded_palette();
+        palette.primary[0] = "F2EEE8".to_string();
+        palette.text[0] = "12100F".to_string();
+        palette.mode = "light".to_string();
+        let palette = complete_loaded_palette(palette).unwrap();
+        assert_eq!(palette.ansi.background, "F2EEE8");
+        assert_eq!(palette.ansi.foreground, "12100F");
+    }
+
+    #[test]
+    fn loaded_ansi_rejects_invalid_colors() {
+        let mut palette = loaded_palette();
+        palette.ansi.selection = "not-a-color".to_string();
+        assert!(complete_loaded_palette(palette).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! Prints a palette to the terminal for the `preview` subcommand.
+//!
+//! On a terminal every color role is drawn as a swatch: each primary with a text-on-primary sample and its accent row, then the ANSI colors. Truecolor escapes are used when `COLORTERM` advertises them, with a nearest-match 256-color fallback, and piped output gets a plain hex listing.
+use crate::color;
+use crate::config::Palette;
+use std::io::IsTerminal;
+
+/// Width in cells of a primary swatch.
+const PRIMARY_SWATCH: &str = "        ";
+/// Width in cells of an accent or ANSI swatch.
+const SMALL_SWATCH: &str = "    ";
+const RESET: &str = "\x1b[0m";
+
+/// How colors are drawn by `print_palette`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ColorMode {
+    /// Truecolor or 256 colors on a terminal, depending on `COLORTERM`; plain text otherwise.
+    #[default]
+    Auto,
+    /// 24-bit escape sequences.
+    Truecolor,
+    /// The xterm 256-color palette, for terminals without truecolor.
+    #[value(name = "256")]
+    Ansi256,
+    /// Hex values only.
+    Never,
+}
+
+impl ColorMode {
+    /// Resolves `Auto` for the current stdout and environment.
+    fn resolve(self) -> ColorMode {
+        if self != ColorMode::Auto {
+            return self;
+        }
+        if !std::io::stdout().is_terminal() {
+            return ColorMode::Never;
+        }
+        match std::env::var("COLORTERM") {
+            Ok(value) if value == "truecolor" || value == "24bit" => ColorMode::Truecolor,
+            _ => ColorMode::Ansi256,
+        }
+    }
+}
+
+/// Builds escape sequences for one resolved color mode.
+struct Painter {
+    mode: ColorMode,
+}
+
+impl Painter {
+    /// The SGR parameters selecting `hex` as foreground (`38`) or background (`48`).
+    fn sgr(&self, layer: u8, hex: &str) -> Option<String> {
+        let [r, g, b] = color::parse_hex(hex)
+            .ok()?
+            .map(|v| (v * 255.0).round() as u8);
+        match self.mode {
+            ColorMode::Truecolor => Some(format!("{};2;{};{};{}", layer, r, g, b)),
+            ColorMode::Ansi256 => Some(format!("{};5;{}", layer, ansi256_index(r, g, b))),
+            ColorMode::Auto | ColorMode::Never => None,
+        }
+    }
+
+    /// `text` on a `background` swatch, optionally in a `foreground` color.
+    fn paint(&self, text: &str, background: &str, foreground: Option<&str>) -> String {
+        let mut params: Vec<String> = self.sgr(48, background).into_iter().collect();
+        params.extend(foreground.and_then(|fg| self.sgr(38, fg)));
+        if params.is_empty() {
+            text.to_string()
+        } else {
+            format!("\x1b[{}m{}{}", params.join(";"), text, RESET)
+        }
+    }
+
+    fn swatch(&self, hex: &str, width: &str) -> String {
+        self.paint(width, hex, None)
+    }
+}
+
+/// Returns the xterm 256-color index closest to an RGB color, from the 6x6x6 cube or the gray ramp.
+fn ansi256_index(r: u8, g: u8, b: u8) -> u8 {
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |v: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs())
+            .unwrap_or(0)
+    };
+    let distance = |c: [u8; 3]| {
+        [r, g, b]
+            .iter()
+            .zip(c)
+            .map(|(&a, b)| (a as i32 - b as i32).pow(2))
+            .sum::<i32>()
+    };
+
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = [CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]];
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let average = (r as i32 + g as i32 + b as i32) / 3;
+    let gray_step = ((average - 8).max(0) / 10).min(23);
+    let gray_level = (8 + gray_step * 10) as u8;
+    let gray_index = 232 + gray_step;
+
+    if distance([gray_level; 3]) < distance(cube) {
+        gray_index as u8
+    } else {
+        cube_index as u8
+    }
+}
+
+/// Prints all palette colors to stdout, as swatches when `mode` allows.
+pub fn print_palette(palette: &Palette, mode: ColorMode) {
+    let mode = mode.resolve();
+    if mode == ColorMode::Never {
+        print_hex_listing(palette);
+        return;
+    }
+    let painter = Painter { mode };
+
+    println!("Wallpaper: {}", palette.wallpaper);
+    println!("Mode: {}", palette.mode);
+    println!();
+
+    for (i, pry) in palette.primary.iter().enumerate() {
+        let txt = palette.text.get(i).map(String::as_str).unwrap_or("");
+        let contrast = palette
+            .text_contrast
+            .get(i)
+            .map(|c| format!(" ({:.2}:1)", c))
+            .unwrap_or_default();
+        println!(
+            "{} {} #{} / #{}{}",
+            painter.swatch(pry, PRIMARY_SWATCH),
+            painter.paint(" Aa Sample text ", pry, Some(txt)),
+            pry,
+            txt,
+            contrast
+        );
+        if let Some(accents) = palette.accents.get(i) {
+            let row: Vec<String> = accents
+                .iter()
+                .map(|acc| painter.swatch(acc, SMALL_SWATCH))
+                .collect();
+            println!("{} {}", " ".repeat(PRIMARY_SWATCH.len()), row.join(""));
+        }
+        println!();
+    }
+
+    if !palette.ansi.is_empty() {
+        let colors = palette.ansi.colors();
+        for (label, row) in [("0-7 ", &colors[..8]), ("8-15", &colors[8..])] {
+            let swatches: Vec<String> = row
+                .iter()
+                .map(|hex| painter.swatch(hex, SMALL_SWATCH))
+                .collect();
+            println!("ANSI {} {}", label, swatches.join(""));
+        }
+        println!(
+            "{} cursor {} selection {}",
+            painter.paint(
+                " foreground on background ",
+                &palette.ansi.background,
+                Some(&palette.ansi.foreground)
+            ),
+            painter.swatch(&palette.ansi.cursor, "  "),
+            painter.swatch(&palette.ansi.selection, "  ")
+        );
+    }
+}
+
+/// Lists every color role by hex value, for output that is not a terminal.
+fn print_hex_listing(palette: &Palette) {
+    println!("Wallpaper: {}", palette.wallpaper);
+    println!("Mode: {}", palette.mode);
+
+    for (i, pry) in palette.primary.iter().enumerate() {
+        let txt = palette.text.get(i).map(String::as_str).unwrap_or("");
+        match palette.text_contrast.get(i) {
+            Some(contrast) => println!(
+                "Primary {}: #{}  Text: #{} ({:.2}:1)",
+                i + 1,
+                pry,
+                txt,
+                contrast
+            ),
+            None => println!("Primary {}: #{}  Text: #{}", i + 1, pry, txt),
+        }
+        if let Some(accents) = palette.accents.get(i) {
+            let accents: Vec<String> = accents.iter().map(|acc| format!("#{}", acc)).collect();
+            println!("  Accents: {}", accents.join(" "));
+        }
+    }
+
+    if !palette.ansi.is_empty() {
+        let colors: Vec<String> = palette
+            .ansi
+            .colors()
+            .iter()
+            .map(|hex| format!("#{}", hex))
+            .collect();
+        println!("ANSI 0-7:  {}", colors[..8].join(" "));
+        println!("ANSI 8-15: {}", colors[8..].join(" "));
+        println!(
+            "Foreground: #{}  Background: #{}  Cursor: #{}  Selection: #{}",
+            palette.ansi.foreground,
+            palette.ansi.background,
+            palette.ansi.cursor,
+            palette.ansi.selection
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Writes pywal-compatible color files so tools built for pywal work with Wallrust.
+//!
+//! The files follow pywal's cache layout in `~/.cache/wal`: `colors.json` (with `wallpaper`, `alpha`, `special`, and `colors.color0`-`color15`), `colors`, `colors.sh` (including pywal's `FZF_DEFAULT_OPTS` and `LS_COLORS` exports), `colors.Xresources`, plus the `wal` wallpaper file and the terminal `sequences`. All colors come from the palette's ANSI colors.
+use crate::atomic::Transaction;
+use crate::config::{AppPaths, Palette};
+use crate::error::WallbashError;
+use crate::terminals;
+use log::info;
+use std::fmt::Write;
+
+/// pywal's opacity value; Wallrust palettes are always opaque.
+const ALPHA: &str = "100";
+
+/// Writes every pywal file for the palette into `paths.pywal_cache_dir`.
+pub fn write_files(
+    palette: &Palette,
+    paths: &AppPaths,
+    transaction: &mut Transaction,
+) -> Result<(), WallbashError> {
+    if palette.ansi.is_empty() {
+        return Err(WallbashError::InvalidInput(
+            "Palette has no terminal colors to write pywal files from".to_string(),
+        ));
+    }
+    let dir = &paths.pywal_cache_dir;
+    let files = [
+        ("colors.json", colors_json(palette)?),
+        ("colors", colors_list(palette)),
+        ("colors.sh", colors_sh(palette)),
+        ("colors.Xresources", colors_xresources(palette)),
+        ("wal", palette.wallpaper.clone()),
+        ("sequences", terminals::sequences(palette)),
+    ];
+    for (name, contents) in files {
+        transaction.write(&dir.join(name), contents.as_bytes())?;
+    }
+    if !transaction.is_dry_run() {
+        info!("Generated pywal files in {}", dir.display());
+    }
+    Ok(())
+}
+
+fn colors_json(palette: &Palette) -> Result<String, WallbashError> {
+    let ansi = &palette.ansi;
+    let mut json = String::new();
+    json.push_str("{\n");
+    let _ = writeln!(
+        json,
+        "    \"wallpaper\": {},",
+        serde_json::to_string(&palette.wallpaper)?
+    );
+    let _ = writeln!(json, "    \"alpha\": \"{}\",", ALPHA);
+    json.push_str("    \"special\": {\n");
+    let _ = writeln!(json, "        \"background\": \"#{}\",", ansi.background);
+    let _ = writeln!(json, "        \"foreground\": \"#{}\",", ansi.foreground);
+    let _ = writeln!(json, "        \"cursor\": \"#{}\"", ansi.cursor);
+    json.push_str("    },\n");
+    json.push_str("    \"colors\": {\n");
+    let colors = ansi.colors();
+    for (i, hex) in colors.iter().enumerate() {
+        let separator = if i + 1 < colors.len() { "," } else { "" };
+        let _ = writeln!(json, "        \"color{}\": \"#{}\"{}", i, hex, separator);
+    }
+    json.push_str("    }\n");
+    json.push_str("}\n");
+    Ok(json)
+}
+
+/// One color per line, as in pywal's `colors` file.
+fn colors_list(palette: &Palette) -> String {
+    palette
+        .ansi
+        .colors()
+        .iter()
+        .map(|hex| format!("#{}\n", hex))
+        .collect()
+}
+
+fn colors_sh(palette: &Palette) -> String {
+    let ansi = &palette.ansi;
+    let mut sh = String::new();
+    sh.push_str("# Shell variables\n# Generated by Wallrust\n");
+    let _ = writeln!(
+        sh,
+        "wallpaper='{}'",
+        palette.wallpaper.replace('\'', "'\\''")
+    );
+    sh.push_str("\n# Special\n");
+    let _ = writeln!(sh, "background='#{}'", ansi.background);
+    let _ = writeln!(sh, "foreground='#{}'", ansi.foreground);
+    let _ = writeln!(sh, "cursor='#{}'", ansi.cursor);
+    sh.push_str("\n# Colors\n");
+    for (i, hex) in ansi.colors().iter().enumerate() {
+        let _ = writeln!(sh, "color{}='#{}'", i, hex);
+    }
+    // Same as pywal: fzf picks its colors from the terminal palette by index.
+    sh.push_str(
+        "\n# FZF colors\n\
+         export FZF_DEFAULT_OPTS=\"\n\
+         \x20   $FZF_DEFAULT_OPTS\n\
+         \x20   --color fg:7,bg:0,hl:1,fg+:232,bg+:1,hl+:255\n\
+         \x20   --color info:7,prompt:2,spinner:1,pointer:232,marker:1\n\
+         \"\n\
+         \n# Fix LS_COLORS being unreadable.\n\
+         export LS_COLORS=\"${LS_COLORS}:su=30;41:ow=30;42:st=30;44:\"\n",
+    );
+    sh
+}
+
+fn colors_xresources(palette: &Palette) -> String {
+    let ansi = &palette.ansi;
+    let mut xresources = String::new();
+    xresources.push_str("! X colors.\n! Generated by Wallrust\n");
+    for prefix in ["*", "*."] {
+        let _ = writeln!(xresources, "{}foreground: #{}", prefix, ansi.foreground);
+        let _ = writeln!(xresources, "{}background: #{}", prefix, ansi.background);
+        let _ = writeln!(xresources, "{}cursorColor: #{}", prefix, ansi.cursor);
+    }
+    xresources.push_str("\n! Colors 0-15.\n");
+    for (i, hex) in ansi.colors().iter().enumerate() {
+        let _ = writeln!(xresources, "*.color{}: #{}", i, hex);
+        let _ = writeln!(xresources, "*color{}: #{}", i, hex);
+    }
+    xresources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette() -> Palette {
+        let mut palette = Palette {
+            wallpaper: "/walls/it's.png".to_string(),
+            ..Palette::default()
+        };
+        for (i, value) in palette.ansi.values_mut().into_iter().enumerate() {
+            *value = format!("{:06X}", i);
+        }
+        palette
+    }
+
+    #[test]
+    fn colors_json_has_pywal_keys() {
+        let json: serde_json::Value =
+            serde_json::from_str(&colors_json(&palette()).unwrap()).unwrap();
+        let keys = |value: &serde_json::Value| {
+            let mut keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(keys(&json), ["alpha", "colors", "special", "wallpaper"]);
+        assert_eq!(
+            keys(&json["special"]),
+            ["background", "cursor", "foreground"]
+        );
+        let mut colors: Vec<String> = (0..16).map(|i| format!("color{}", i)).collect();
+        colors.sort();
+        assert_eq!(keys(&json["colors"]), colors);
+        assert_eq!(json["wallpaper"], "/walls/it's.png");
+        assert_eq!(json["colors"]["color15"], "#00000F");
+        assert_eq!(json["special"]["background"], "#000011");
+    }
+
+    #[test]
+    fn colors_lists_the_16_ansi_colors() {
+        let colors = colors_list(&palette());
+        assert_eq!(colors.lines().count(), 16);
+        assert_eq!(colors.lines().next(), Some("#000000"));
+    }
+
+    #[test]
+    fn colors_sh_exports_fzf_and_ls_colors() {
+        let sh = colors_sh(&palette());
+        assert!(sh.contains("color15='#00000F'\n"));
+        assert!(sh.contains("--color fg:7,bg:0,hl:1,fg+:232,bg+:1,hl+:255\n"));
+        assert!(sh.contains("export LS_COLORS=\"${LS_COLORS}:su=30;41:ow=30;42:st=30;44:\"\n"));
+    }
+}
@@ -0,0 +1,156 @@
+//! Exports palettes as swatch files for design tools, selected with `--swatches`.
+//!
+//! GIMP and Inkscape read GIMP palettes (`.gpl`), Adobe apps read Swatch Exchange (`.ase`) and Photoshop color swatches (`.aco`). Every format holds the same swatches named by role: each primary, its text color and accents ("Accent 2-5" is the fifth accent of the second primary), then the ANSI colors.
+use crate::color;
+use crate::config::Palette;
+use crate::error::WallbashError;
+
+/// A swatch file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SwatchFormat {
+    /// GIMP palette, also used by Inkscape and Krita.
+    Gpl,
+    /// Adobe Swatch Exchange.
+    Ase,
+    /// Photoshop color swatches.
+    Aco,
+}
+
+impl SwatchFormat {
+    /// The file written to the output directory.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            SwatchFormat::Gpl => "wallrust.gpl",
+            SwatchFormat::Ase => "wallrust.ase",
+            SwatchFormat::Aco => "wallrust.aco",
+        }
+    }
+
+    /// Encodes the palette's swatches in this format.
+    pub fn contents(self, palette: &Palette) -> Result<Vec<u8>, WallbashError> {
+        let swatches = swatches(palette)?;
+        Ok(match self {
+            SwatchFormat::Gpl => gpl_contents(palette, &swatches),
+            SwatchFormat::Ase => ase_contents(&swatches),
+            SwatchFormat::Aco => aco_contents(&swatches),
+        })
+    }
+}
+
+/// A named color with 8-bit RGB channels.
+struct Swatch {
+    name: String,
+    rgb: [u8; 3],
+}
+
+/// Lists every palette color named by its role, in palette order.
+fn swatches(palette: &Palette) -> Result<Vec<Swatch>, WallbashError> {
+    let swatch = |name: String, hex: &str| -> Result<Swatch, WallbashError> {
+        let rgb = color::parse_hex(hex)?.map(|v| (v * 255.0).round() as u8);
+        Ok(Swatch { name, rgb })
+    };
+
+    let mut swatches = Vec::new();
+    for (i, pry) in palette.primary.iter().enumerate() {
+        swatches.push(swatch(format!("Primary {}", i + 1), pry)?);
+        if let Some(txt) = palette.text.get(i) {
+            swatches.push(swatch(format!("Text {}", i + 1), txt)?);
+        }
+        for (j, acc) in palette.accents.get(i).into_iter().flatten().enumerate() {
+            swatches.push(swatch(format!("Accent {}-{}", i + 1, j + 1), acc)?);
+        }
+    }
+    if !palette.ansi.is_empty() {
+        for (i, hex) in palette.ansi.colors().iter().enumerate() {
+            swatches.push(swatch(format!("ANSI {}", i), hex)?);
+        }
+        let ansi = &palette.ansi;
+        for (name, hex) in [
+            ("Foreground", &ansi.foreground),
+            ("Background", &ansi.background),
+            ("Cursor", &ansi.cursor),
+            ("Selection", &ansi.selection),
+        ] {
+            swatches.push(swatch(name.to_string(), hex)?);
+        }
+    }
+    Ok(swatches)
+}
+
+/// The UTF-16 code units of a swatch name followed by a NUL terminator, as ASE and ACO store names.
+fn utf16_name(name: &str) -> Vec<u16> {
+    name.encode_utf16().chain([0]).collect()
+}
+
+fn gpl_contents(palette: &Palette, swatches: &[Swatch]) -> Vec<u8> {
+    let title = std::path::Path::new(&palette.wallpaper)
+        .file_name()
+        .map(|name| format!("Wallrust {}", name.to_string_lossy()))
+        .unwrap_or_else(|| "Wallrust".to_string());
+    let mut gpl = format!("GIMP Palette\nName: {}\nColumns: 11\n#\n", title);
+    for Swatch {
+        name,
+        rgb: [r, g, b],
+    } in swatches
+    {
+        gpl.push_str(&format!("{:3} {:3} {:3}\t{}\n", r, g, b, name));
+    }
+    gpl.into_bytes()
+}
+
+/// Adobe Swatch Exchange: a `ASEF` header and one color entry block per swatch, all big-endian.
+fn ase_contents(swatches: &[Swatch]) -> Vec<u8> {
+    const COLOR_ENTRY: u16 = 0x0001;
+    const NORMAL_COLOR: u16 = 2;
+
+    let mut ase = Vec::new();
+    ase.extend_from_slice(b"ASEF");
+    ase.extend_from_slice(&1u16.to_be_bytes());
+    ase.extend_from_slice(&0u16.to_be_bytes());
+    ase.extend_from_slice(&(swatches.len() as u32).to_be_bytes());
+
+    for swatch in swatches {
+        let name = utf16_name(&swatch.name);
+        let mut block = Vec::new();
+        block.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        for unit in &name {
+            block.extend_from_slice(&unit.to_be_bytes());
+        }
+        block.extend_from_slice(b"RGB ");
+        for channel in swatch.rgb {
+            block.extend_from_slice(&(channel as f32 / 255.0).to_be_bytes());
+        }
+        block.extend_from_slice(&NORMAL_COLOR.to_be_bytes());
+
+        ase.extend_from_slice(&COLOR_ENTRY.to_be_bytes());
+        ase.extend_from_slice(&(block.len() as u32).to_be_bytes());
+        ase.extend_from_slice(&block);
+    }
+    ase
+}
+
+/// Photoshop color swatches: an unnamed version 1 section followed by the named version 2 section.
+fn aco_contents(swatches: &[Swatch]) -> Vec<u8> {
+    const RGB_SPACE: u16 = 0;
+
+    let mut aco = Vec::new();
+    for version in [1u16, 2] {
+        aco.extend_from_slice(&version.to_be_bytes());
+        aco.extend_from_slice(&(swatches.len() as u16).to_be_bytes());
+        for swatch in swatches {
+            aco.extend_from_slice(&RGB_SPACE.to_be_bytes());
+            for channel in swatch.rgb {
+                aco.extend_from_slice(&(channel as u16 * 257).to_be_bytes());
+            }
+            aco.extend_from_slice(&0u16.to_be_bytes());
+            if version == 2 {
+                let name = utf16_name(&swatch.name);
+                aco.extend_from_slice(&(name.len() as u32).to_be_bytes());
+                for unit in &name {
+                    aco.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+        }
+    }
+    aco
+}
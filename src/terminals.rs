@@ -0,0 +1,128 @@
+//! Recolors running terminals with OSC escape sequences, like pywal.
+//!
+//! The ANSI colors become OSC 4 palette entries, and the foreground, background, and cursor colors OSC 10, 11, and 12. The sequences are written to every pseudo-terminal the user owns and saved to the cache so new shells can `cat` them on startup.
+use crate::atomic::Transaction;
+use crate::config::{AppPaths, Palette};
+use crate::error::WallbashError;
+use log::{debug, info, warn};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Builds the OSC sequences that apply the palette's terminal colors.
+pub fn sequences(palette: &Palette) -> String {
+    let osc = |code: String, hex: &str| format!("\x1b]{};#{}\x1b\\", code, hex);
+
+    let mut sequences = String::new();
+    for (i, hex) in palette.ansi.colors().iter().enumerate() {
+        sequences.push_str(&osc(format!("4;{}", i), hex));
+    }
+    sequences.push_str(&osc("10".to_string(), &palette.ansi.foreground));
+    sequences.push_str(&osc("11".to_string(), &palette.ansi.background));
+    sequences.push_str(&osc("12".to_string(), &palette.ansi.cursor));
+    sequences
+}
+
+/// Returns the pseudo-terminals under `/dev/pts` owned by the current user.
+#[cfg(unix)]
+fn user_terminals() -> Vec<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+
+    // Our own process is owned by the current user, which avoids an extra dependency for getuid().
+    let Ok(uid) = fs::metadata("/proc/self").map(|m| m.uid()) else {
+        warn!("Could not determine the current user, not recoloring terminals");
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir("/dev/pts") else {
+        warn!("Could not read /dev/pts, not recoloring terminals");
+        return Vec::new();
+    };
+
+    let mut terminals: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().parse::<u32>().is_ok())
+        .filter(|entry| entry.metadata().is_ok_and(|m| m.uid() == uid))
+        .map(|entry| entry.path())
+        .collect();
+    terminals.sort();
+    terminals
+}
+
+#[cfg(not(unix))]
+fn user_terminals() -> Vec<PathBuf> {
+    warn!("Recoloring terminals is only supported on Unix");
+    Vec::new()
+}
+
+/// Saves the palette's escape sequences to the cache for new shells.
+pub fn save(
+    palette: &Palette,
+    paths: &AppPaths,
+    transaction: &mut Transaction,
+) -> Result<(), WallbashError> {
+    if palette.ansi.is_empty() {
+        warn!("Palette has no terminal colors, not recoloring terminals");
+        return Ok(());
+    }
+    transaction.write(&paths.sequences_file, sequences(palette).as_bytes())?;
+    if !transaction.is_dry_run() {
+        info!(
+            "Saved terminal sequences to {}",
+            paths.sequences_file.display()
+        );
+    }
+    Ok(())
+}
+
+/// Sends the palette to all of the user's terminals.
+///
+/// A terminal that cannot be written to is skipped. With `dry_run`, only reports what would happen.
+pub fn recolor(palette: &Palette, dry_run: bool) {
+    if palette.ansi.is_empty() {
+        return;
+    }
+    let sequences = sequences(palette);
+    let terminals = user_terminals();
+
+    if dry_run {
+        for terminal in &terminals {
+            eprintln!("Would recolor terminal: {}", terminal.display());
+        }
+        return;
+    }
+    let mut applied = 0;
+    for terminal in &terminals {
+        let result = OpenOptions::new()
+            .write(true)
+            .open(terminal)
+            .and_then(|mut tty| tty.write_all(sequences.as_bytes()));
+        match result {
+            Ok(()) => applied += 1,
+            Err(e) => debug!("Skipping terminal {}: {}", terminal.display(), e),
+        }
+    }
+    info!("Recolored {} terminal(s)", applied);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequences_set_the_palette_and_special_colors() {
+        let mut palette = Palette::default();
+        for (i, value) in palette.ansi.values_mut().into_iter().enumerate() {
+            *value = format!("{:06X}", i);
+        }
+
+        let expected: String = (0..16)
+            .map(|i| format!("\x1b]4;{};#{:06X}\x1b\\", i, i))
+            .chain([
+                "\x1b]10;#000010\x1b\\".to_string(),
+                "\x1b]11;#000011\x1b\\".to_string(),
+                "\x1b]12;#000012\x1b\\".to_string(),
+            ])
+            .collect();
+        assert_eq!(sequences(&palette), expected);
+    }
+}
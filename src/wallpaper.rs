@@ -2,6 +2,7 @@
 //!
 //! This module provides functions to query Hyprland for the active wallpaper path, supporting seamless integration with dynamic wallpaper setups.
 use crate::error::WallbashError;
+use log::{Level, debug, log};
 use std::path::PathBuf;
 use std::process::Command;
 
@@ -9,7 +10,18 @@ use std::process::Command;
 ///
 /// Returns the path to the active wallpaper, or an error if detection fails.
 pub fn detect_hyprland_wallpaper() -> Result<PathBuf, WallbashError> {
-    println!("Attempting to detect Hyprland wallpaper via hyprctl...");
+    detect_with_levels(Level::Info, Level::Warn)
+}
+
+/// Like `detect_hyprland_wallpaper`, but logs only at debug level, for callers that detect on every poll.
+pub fn poll_hyprland_wallpaper() -> Result<PathBuf, WallbashError> {
+    detect_with_levels(Level::Debug, Level::Debug)
+}
+
+/// Detects the wallpaper, logging the detected path at `found` and failed probes at `failed`.
+#[allow(clippy::collapsible_if, clippy::manual_split_once)]
+fn detect_with_levels(found: Level, failed: Level) -> Result<PathBuf, WallbashError> {
+    debug!("Attempting to detect Hyprland wallpaper via hyprctl...");
 
     let output_hyprpaper = Command::new("hyprctl")
         .args(["hyprpaper", "listactive"])
@@ -23,25 +35,28 @@ pub fn detect_hyprland_wallpaper() -> Result<PathBuf, WallbashError> {
                     if let Some(path_str) = path_part.split(" on monitor").next() {
                         let path = PathBuf::from(path_str.trim());
                         if path.exists() {
-                            println!("Detected hyprpaper wallpaper: {}", path.display());
+                            log!(found, "Detected hyprpaper wallpaper: {}", path.display());
                             return Ok(path);
                         } else {
-                            eprintln!(
-                                "Warning: hyprpaper reported path does not exist: {}",
+                            log!(
+                                failed,
+                                "hyprpaper reported path does not exist: {}",
                                 path.display()
                             );
                         }
                     }
                 }
             }
-            eprintln!(
-                "Warning: Could not parse hyprpaper listactive output: {}",
+            log!(
+                failed,
+                "Could not parse hyprpaper listactive output: {}",
                 stdout
             );
         } else {
             let stderr = String::from_utf8_lossy(&out.stderr);
-            eprintln!(
-                "Warning: `hyprctl hyprpaper listactive` failed: {}",
+            log!(
+                failed,
+                "`hyprctl hyprpaper listactive` failed: {}",
                 stderr.trim()
             );
         }
@@ -51,8 +66,9 @@ pub fn detect_hyprland_wallpaper() -> Result<PathBuf, WallbashError> {
                 "`hyprctl` command not found.".to_string(),
             ));
         }
-        eprintln!(
-            "Warning: Failed to execute `hyprctl hyprpaper listactive`: {}",
+        log!(
+            failed,
+            "Failed to execute `hyprctl hyprpaper listactive`: {}",
             e
         );
     }
@@ -67,7 +83,7 @@ pub fn detect_hyprland_wallpaper() -> Result<PathBuf, WallbashError> {
             if let Some(path_str) = stdout.splitn(2, ": ").nth(1) {
                 let path = PathBuf::from(path_str.trim());
                 if path.exists() {
-                    println!("Detected wallpaper variable: {}", path.display());
+                    log!(found, "Detected wallpaper variable: {}", path.display());
                     return Ok(path);
                 }
             }
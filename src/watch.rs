@@ -0,0 +1,149 @@
+//! Watches for wallpaper changes and re-runs the full pipeline for `wallrust watch`.
+//!
+//! The watched source is either a file or symlink (e.g. `~/.current_wallpaper`), or the wallpaper reported by Hyprland. Both are polled: a change is only acted on once it has been stable for the debounce interval, so a wallpaper script that rewrites a file in several steps triggers a single run. SIGINT and SIGTERM stop the loop after any run in progress has finished.
+use crate::cli::ExtractArgs;
+use crate::commands;
+use crate::config::{FileStamp, UserConfig};
+use crate::error::WallbashError;
+use crate::output::{OutputOptions, Templates};
+use crate::wallpaper;
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest single sleep between checks for a shutdown request.
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Timing and output options for watch mode.
+pub struct WatchOptions {
+    pub interval: Duration,
+    pub debounce: Duration,
+    pub output_dir: Option<String>,
+    pub html: bool,
+    pub output: OutputOptions,
+}
+
+/// The resolved wallpaper and the stamp of the file it points to.
+#[derive(Debug, Clone, PartialEq)]
+struct WallpaperState {
+    path: PathBuf,
+    stamp: FileStamp,
+}
+
+/// Resolves the current wallpaper, following symlinks so that retargeting a link counts as a change.
+fn current_wallpaper(watched: Option<&PathBuf>) -> Result<WallpaperState, WallbashError> {
+    let path = match watched {
+        Some(path) => path.canonicalize()?,
+        None => wallpaper::poll_hyprland_wallpaper()?,
+    };
+    let stamp = FileStamp::of(&path)?;
+    Ok(WallpaperState { path, stamp })
+}
+
+/// Sleeps for `duration`, returning early if a shutdown was requested.
+fn sleep_unless_stopped(duration: Duration, running: &AtomicBool) {
+    let deadline = Instant::now() + duration;
+    while running.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        thread::sleep((deadline - now).min(SHUTDOWN_CHECK_INTERVAL));
+    }
+}
+
+/// Polls the watched wallpaper and runs the extract, cache, and output pipeline whenever it changes.
+pub fn watch(args: &ExtractArgs, user_config: &UserConfig, options: &WatchOptions) -> Result<()> {
+    let watched = match &args.input_image {
+        Some(path) => Some(PathBuf::from(
+            shellexpand::full(path)
+                .map_err(|e| {
+                    WallbashError::PathExpansion(format!("Watch path expansion failed: {}", e))
+                })?
+                .into_owned(),
+        )),
+        None if args.detect_hyprland => None,
+        None => anyhow::bail!("Nothing to watch: pass a wallpaper file or --detect-hyprland."),
+    };
+
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_flag = Arc::clone(&running);
+    ctrlc::set_handler(move || handler_flag.store(false, Ordering::SeqCst))
+        .context("Failed to install signal handler")?;
+
+    match &watched {
+        Some(path) => info!("Watching {} for wallpaper changes...", path.display()),
+        None => info!("Watching the Hyprland wallpaper for changes..."),
+    }
+
+    let mut templates = Templates::default();
+    let mut applied: Option<WallpaperState> = None;
+    let mut pending: Option<(WallpaperState, Instant)> = None;
+    // Detection runs on every poll, so only changes in what it finds are logged above debug level.
+    let mut detected: Option<PathBuf> = None;
+    let mut last_error: Option<String> = None;
+    while running.load(Ordering::SeqCst) {
+        let current = current_wallpaper(watched.as_ref());
+        if let Ok(state) = &current {
+            last_error = None;
+            if watched.is_none() && detected.as_ref() != Some(&state.path) {
+                info!("Detected Hyprland wallpaper: {}", state.path.display());
+                detected = Some(state.path.clone());
+            }
+        }
+        match current {
+            Ok(state) if applied.as_ref() != Some(&state) => match &pending {
+                Some((candidate, since))
+                    if *candidate == state && since.elapsed() >= options.debounce =>
+                {
+                    info!("Wallpaper changed: {}", state.path.display());
+                    let run_args = ExtractArgs {
+                        input_image: Some(state.path.display().to_string()),
+                        detect_hyprland: false,
+                        ..args.clone()
+                    };
+                    if let Err(e) = commands::run(
+                        &run_args,
+                        user_config,
+                        options.output_dir.clone(),
+                        options.html,
+                        &options.output,
+                        &mut templates,
+                    ) {
+                        warn!(
+                            "Failed to apply wallpaper {}: {:#}",
+                            state.path.display(),
+                            e
+                        );
+                    }
+                    applied = Some(state);
+                    pending = None;
+                }
+                Some((candidate, _)) if *candidate == state => {}
+                _ => pending = Some((state, Instant::now())),
+            },
+            Ok(_) => pending = None,
+            Err(e) => {
+                let message = e.to_string();
+                if last_error.as_ref() == Some(&message) {
+                    debug!("Could not read the watched wallpaper: {}", message);
+                } else {
+                    warn!("Could not read the watched wallpaper: {}", message);
+                }
+                last_error = Some(message);
+            }
+        }
+        let wait = match &pending {
+            Some(_) => options.interval.min(options.debounce),
+            None => options.interval,
+        };
+        sleep_unless_stopped(wait, &running);
+    }
+
+    info!("Stopping watch.");
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! Built-in output writers for popular apps, selected with `--emit`.
+//!
+//! Each writer is a Tera template compiled into the binary and rendered into the output directory with the same context and filters as user templates. Dropping a template with the same name into the template directory replaces the built-in one.
+use std::path::Path;
+
+/// An app Wallrust can write a color config for without user templates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Emitter {
+    Kitty,
+    Alacritty,
+    Foot,
+    Wezterm,
+    Rofi,
+    Dunst,
+    Waybar,
+    Hyprland,
+    Base16,
+    Base24,
+}
+
+impl Emitter {
+    /// The writer's name, as given to `--emit`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Emitter::Kitty => "kitty",
+            Emitter::Alacritty => "alacritty",
+            Emitter::Foot => "foot",
+            Emitter::Wezterm => "wezterm",
+            Emitter::Rofi => "rofi",
+            Emitter::Dunst => "dunst",
+            Emitter::Waybar => "waybar",
+            Emitter::Hyprland => "hyprland",
+            Emitter::Base16 => "base16",
+            Emitter::Base24 => "base24",
+        }
+    }
+
+    /// The file written to the output directory.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Emitter::Kitty => "kitty.conf",
+            Emitter::Alacritty => "alacritty.toml",
+            Emitter::Foot => "foot.ini",
+            Emitter::Wezterm => "wezterm.toml",
+            Emitter::Rofi => "rofi.rasi",
+            Emitter::Dunst => "dunstrc",
+            Emitter::Waybar => "waybar.css",
+            Emitter::Hyprland => "hyprland.conf",
+            Emitter::Base16 => "base16.yaml",
+            Emitter::Base24 => "base24.yaml",
+        }
+    }
+
+    /// The built-in Tera template.
+    pub fn template(self) -> &'static str {
+        match self {
+            Emitter::Kitty => include_str!("writers/kitty.conf"),
+            Emitter::Alacritty => include_str!("writers/alacritty.toml"),
+            Emitter::Foot => include_str!("writers/foot.ini"),
+            Emitter::Wezterm => include_str!("writers/wezterm.toml"),
+            Emitter::Rofi => include_str!("writers/rofi.rasi"),
+            Emitter::Dunst => include_str!("writers/dunstrc"),
+            Emitter::Waybar => include_str!("writers/waybar.css"),
+            Emitter::Hyprland => include_str!("writers/hyprland.conf"),
+            Emitter::Base16 => include_str!("writers/base16.yaml"),
+            Emitter::Base24 => include_str!("writers/base24.yaml"),
+        }
+    }
+
+    /// Returns the user template overriding this writer: one whose file is named like the writer
+    /// (`kitty`, `kitty.tera`, ...) or like its output file (`kitty.conf`), in any subdirectory.
+    pub fn user_override<'a>(
+        self,
+        template_names: impl IntoIterator<Item = &'a str>,
+    ) -> Option<&'a str> {
+        template_names
+            .into_iter()
+            .filter(|name| {
+                let path = Path::new(name);
+                path.file_name().and_then(|s| s.to_str()) == Some(self.file_name())
+                    || path.file_stem().and_then(|s| s.to_str()) == Some(self.name())
+            })
+            .min()
+    }
+}
@@ -0,0 +1,70 @@
+//! Runs `wallrust` from different working directories against one palette cache.
+use image::{Rgb, RgbImage};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wallrust-test-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run_wallrust(home: &Path, cwd: &Path, args: &[&str]) {
+    let status = Command::new(env!("CARGO_BIN_EXE_wallrust"))
+        .args(args)
+        .current_dir(cwd)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_CACHE_HOME", home.join(".cache"))
+        .status()
+        .expect("failed to run wallrust");
+    assert!(status.success(), "wallrust exited with {}", status);
+}
+
+fn cache_entries(home: &Path) -> usize {
+    std::fs::read_dir(home.join(".cache/wallrust/palettes"))
+        .map(|entries| entries.count())
+        .unwrap_or(0)
+}
+
+#[test]
+fn prune_keeps_entries_stored_via_a_relative_path() {
+    let dir = temp_dir("prune-relative");
+    let walls = dir.join("walls");
+    let elsewhere = dir.join("elsewhere");
+    std::fs::create_dir_all(&walls).unwrap();
+    std::fs::create_dir_all(&elsewhere).unwrap();
+    // Four vertical stripes, enough distinct colors for the default extraction.
+    let colors = [[200, 40, 40], [40, 160, 60], [40, 60, 200], [230, 210, 90]];
+    RgbImage::from_fn(64, 16, |x, _| Rgb(colors[x as usize / 16]))
+        .save(walls.join("wall.png"))
+        .unwrap();
+
+    run_wallrust(
+        &dir,
+        &walls,
+        &[
+            "extract",
+            "./wall.png",
+            "--backend",
+            "native",
+            "--no-write",
+            "--quiet",
+        ],
+    );
+    assert_eq!(cache_entries(&dir), 1);
+
+    run_wallrust(&dir, &elsewhere, &["cache", "prune", "--quiet"]);
+    assert_eq!(
+        cache_entries(&dir),
+        1,
+        "prune removed an entry for an existing image"
+    );
+
+    std::fs::remove_file(walls.join("wall.png")).unwrap();
+    run_wallrust(&dir, &elsewhere, &["cache", "prune", "--quiet"]);
+    assert_eq!(cache_entries(&dir), 0);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,187 @@
+//! Round-trips the palette through the `--swatches ase` writer and an independent ASE parser.
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// One color entry of an Adobe Swatch Exchange file.
+#[derive(Debug, PartialEq)]
+struct AseColor {
+    name: String,
+    model: String,
+    values: Vec<f32>,
+    color_type: u16,
+}
+
+/// A big-endian reader over the file contents that fails on truncated input.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> &'a [u8] {
+        assert!(self.bytes.len() >= len, "ASE data is truncated");
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        head
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_be_bytes(self.take(2).try_into().unwrap())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_be_bytes(self.take(4).try_into().unwrap())
+    }
+
+    fn f32(&mut self) -> f32 {
+        f32::from_be_bytes(self.take(4).try_into().unwrap())
+    }
+}
+
+fn parse_ase(bytes: &[u8]) -> Vec<AseColor> {
+    let mut reader = Reader { bytes };
+    assert_eq!(reader.take(4), b"ASEF");
+    assert_eq!(
+        (reader.u16(), reader.u16()),
+        (1, 0),
+        "unexpected ASE version"
+    );
+    let block_count = reader.u32();
+
+    let mut colors = Vec::new();
+    for _ in 0..block_count {
+        let block_type = reader.u16();
+        let length = reader.u32() as usize;
+        let mut block = Reader {
+            bytes: reader.take(length),
+        };
+        // Group start (0xC001) and end (0xC002) blocks carry no colors.
+        if block_type != 0x0001 {
+            continue;
+        }
+        let name_len = block.u16() as usize;
+        let units: Vec<u16> = (0..name_len).map(|_| block.u16()).collect();
+        assert_eq!(units.last(), Some(&0), "name is not NUL-terminated");
+        let name = String::from_utf16(&units[..name_len - 1]).unwrap();
+        let model = String::from_utf8(block.take(4).to_vec()).unwrap();
+        let channels = match model.as_str() {
+            "RGB " | "LAB " => 3,
+            "CMYK" => 4,
+            "Gray" => 1,
+            other => panic!("unknown color model {:?}", other),
+        };
+        let values = (0..channels).map(|_| block.f32()).collect();
+        let color_type = block.u16();
+        assert!(block.bytes.is_empty(), "trailing bytes in color block");
+        colors.push(AseColor {
+            name,
+            model,
+            values,
+            color_type,
+        });
+    }
+    assert!(
+        reader.bytes.is_empty(),
+        "trailing bytes after the last block"
+    );
+    colors
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wallrust-test-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// The swatches expected for a palette as written to `wallrust.json`, as names and hex colors.
+fn expected_swatches(palette: &Value) -> Vec<(String, String)> {
+    let hex = |v: &Value| v.as_str().unwrap().to_string();
+    let mut expected = Vec::new();
+    for (i, pry) in palette["primary"].as_array().unwrap().iter().enumerate() {
+        expected.push((format!("Primary {}", i + 1), hex(pry)));
+        expected.push((format!("Text {}", i + 1), hex(&palette["text"][i])));
+        for (j, acc) in palette["accents"][i].as_array().unwrap().iter().enumerate() {
+            expected.push((format!("Accent {}-{}", i + 1, j + 1), hex(acc)));
+        }
+    }
+    let ansi = &palette["ansi"];
+    for i in 0..16 {
+        expected.push((format!("ANSI {}", i), hex(&ansi[format!("color{}", i)])));
+    }
+    for (name, key) in [
+        ("Foreground", "foreground"),
+        ("Background", "background"),
+        ("Cursor", "cursor"),
+        ("Selection", "selection"),
+    ] {
+        expected.push((name.to_string(), hex(&ansi[key])));
+    }
+    expected
+}
+
+fn run_wallrust(home: &Path, args: &[&str]) {
+    let status = Command::new(env!("CARGO_BIN_EXE_wallrust"))
+        .args(args)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_CACHE_HOME", home.join(".cache"))
+        .status()
+        .expect("failed to run wallrust");
+    assert!(status.success(), "wallrust exited with {}", status);
+}
+
+#[test]
+fn ase_round_trips_palette_swatches() {
+    let dir = temp_dir("ase");
+    let palette_path = dir.join("palette.json");
+    std::fs::write(
+        &palette_path,
+        r#"{
+            "mode": "dark",
+            "wallpaper": "/walls/forest.png",
+            "primary": ["1B2A24", "5E8C6A"],
+            "text": ["E0EEE5", "0B120E"],
+            "accents": [["2E4A3E", "4F7A67", "8CC0A5"], ["35503D", "7FB08C", "C4E3CD"]]
+        }"#,
+    )
+    .unwrap();
+    let output_dir = dir.join("out");
+
+    run_wallrust(
+        &dir,
+        &[
+            "--from-palette",
+            palette_path.to_str().unwrap(),
+            "--output-dir",
+            output_dir.to_str().unwrap(),
+            "--no-templates",
+            "--swatches",
+            "ase",
+            "--quiet",
+        ],
+    );
+
+    let written: Value =
+        serde_json::from_str(&std::fs::read_to_string(output_dir.join("wallrust.json")).unwrap())
+            .unwrap();
+    let colors = parse_ase(&std::fs::read(output_dir.join("wallrust.ase")).unwrap());
+    let parsed: Vec<(String, String)> = colors
+        .iter()
+        .map(|color| {
+            assert_eq!(color.model, "RGB ");
+            assert_eq!(color.color_type, 2, "swatches should be normal colors");
+            let [r, g, b] = [0, 1, 2].map(|c| (color.values[c] * 255.0).round() as u8);
+            (color.name.clone(), format!("{:02X}{:02X}{:02X}", r, g, b))
+        })
+        .collect();
+
+    assert_eq!(parsed, expected_swatches(&written));
+    assert!(
+        parsed
+            .iter()
+            .any(|(name, hex)| name == "Accent 2-3" && hex == "C4E3CD")
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}